@@ -0,0 +1,215 @@
+use crate::literal_value::to_hex;
+use tiny_keccak::{Hasher, Keccak};
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    let mut output = [0; 32];
+    hasher.update(data);
+    hasher.finalize(&mut output);
+    output
+}
+
+/// Computes the 4-byte selector of a canonical function or error signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let hash = keccak256(signature.as_bytes());
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// Formats a selector, topic or any other fixed-size value as lowercase hex with `0x` prefix.
+pub fn format_bytes(bytes: &[u8]) -> String {
+    format!("0x{}", to_hex(bytes))
+}
+
+fn is_elementary_type(type_name: &str) -> bool {
+    let sized = |prefix: &str, valid_size: fn(u32) -> bool| {
+        type_name
+            .strip_prefix(prefix)
+            .and_then(|size| size.parse::<u32>().ok())
+            .map_or(false, valid_size)
+    };
+
+    matches!(
+        type_name,
+        "address" | "bool" | "string" | "bytes" | "function"
+    ) || sized("uint", |bits| bits > 0 && bits <= 256 && bits % 8 == 0)
+        || sized("int", |bits| bits > 0 && bits <= 256 && bits % 8 == 0)
+        || sized("bytes", |size| size > 0 && size <= 32)
+}
+
+fn canonical_type(type_name: &str) -> Option<String> {
+    // Array suffixes stay attached to the base type, e.g. `uint[2]` becomes `uint256[2]`.
+    let suffix_start = if type_name.starts_with('(') {
+        type_name
+            .rfind(')')
+            .map_or(type_name.len(), |close| close + 1)
+    } else {
+        type_name.find('[').unwrap_or(type_name.len())
+    };
+    let (base, suffix) = type_name.split_at(suffix_start);
+    if !suffix
+        .chars()
+        .all(|c| c == '[' || c == ']' || c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let base = match base {
+        "uint" => "uint256".to_string(),
+        "int" => "int256".to_string(),
+        "byte" => "bytes1".to_string(),
+        _ if base.len() >= 2 && base.starts_with('(') && base.ends_with(')') => {
+            format!("({})", canonical_parameters(&base[1..base.len() - 1])?)
+        }
+        _ if is_elementary_type(base) => base.to_string(),
+        _ => return None,
+    };
+    Some(format!("{}{}", base, suffix))
+}
+
+/// Splits on commas that are not nested in parentheses.
+pub fn split_parameters(parameters: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (index, c) in parameters.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&parameters[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&parameters[start..]);
+    parts
+}
+
+fn canonical_parameters(parameters: &str) -> Option<String> {
+    if parameters.trim().is_empty() {
+        return Some("".to_string());
+    }
+
+    let types = split_parameters(parameters)
+        .into_iter()
+        .map(|parameter| {
+            // Drop parameter names and data locations, e.g. `address indexed from`.
+            let parameter = parameter.trim();
+            let type_end = if parameter.starts_with('(') {
+                let mut depth = 0;
+                parameter
+                    .char_indices()
+                    .find(|(_, c)| {
+                        match c {
+                            '(' => depth += 1,
+                            ')' => depth -= 1,
+                            _ => {}
+                        }
+                        depth == 0
+                    })
+                    .map(|(index, _)| index + 1)?
+            } else {
+                0
+            };
+            let type_end = parameter[type_end..]
+                .find(char::is_whitespace)
+                .map_or(parameter.len(), |end| type_end + end);
+            canonical_type(&parameter[..type_end])
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(types.join(","))
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Converts a signature like `transfer(address to, uint amount)` into its canonical form,
+/// i.e. `transfer(address,uint256)`.
+pub fn canonical_signature(signature: &str) -> Option<String> {
+    let signature = signature.trim();
+    let open = signature.find('(')?;
+    let name = signature[..open].trim();
+    if name.is_empty()
+        || !name.chars().all(is_identifier_char)
+        || name.starts_with(|c: char| c.is_ascii_digit())
+        || !signature.ends_with(')')
+    {
+        return None;
+    }
+
+    let parameters = canonical_parameters(&signature[open + 1..signature.len() - 1])?;
+    Some(format!("{}({})", name, parameters))
+}
+
+/// Finds the first thing looking like a function signature in free-form text, e.g. a comment.
+pub fn find_signature(text: &str) -> Option<String> {
+    for (open, _) in text.match_indices('(') {
+        let name_start = text[..open]
+            .rfind(|c: char| !is_identifier_char(c))
+            .map_or(0, |index| index + 1);
+
+        let mut depth = 0;
+        let close = text[open..].char_indices().find_map(|(index, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                Some(open + index)
+            } else {
+                None
+            }
+        });
+
+        if let Some(close) = close {
+            if let Some(signature) = canonical_signature(&text[name_start..=close]) {
+                return Some(signature);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selectors() {
+        assert_eq!(
+            format_bytes(&selector("transfer(address,uint256)")),
+            "0xa9059cbb"
+        );
+        assert_eq!(format_bytes(&selector("balanceOf(address)")), "0x70a08231");
+        assert_eq!(
+            format_bytes(&keccak256(b"Transfer(address,address,uint256)")),
+            "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+        );
+    }
+
+    #[test]
+    fn signatures() {
+        assert_eq!(
+            canonical_signature("transfer(address to, uint amount)"),
+            Some("transfer(address,uint256)".to_string())
+        );
+        assert_eq!(
+            canonical_signature("f((uint, bytes) memory s, int[] x)"),
+            Some("f((uint256,bytes),int256[])".to_string())
+        );
+        assert_eq!(
+            canonical_signature("totalSupply()"),
+            Some("totalSupply()".to_string())
+        );
+        assert_eq!(canonical_signature("not a signature"), None);
+        assert_eq!(canonical_signature("see(the docs)"), None);
+        assert_eq!(
+            find_signature(" function transfer(address,uint256) "),
+            Some("transfer(address,uint256)".to_string())
+        );
+        assert_eq!(find_signature("(see below)"), None);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::abi::{format_bytes, keccak256, selector};
+use eyre::Result;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EventSignature {
+    pub signature: String,
+    /// Number of indexed parameters, i.e. topics other than the event topic itself.
+    pub indexed_count: usize,
+    pub anonymous: bool,
+}
+
+/// Signatures extracted from ABI JSON files and compiler artifacts, indexed by selector or
+/// event topic.
+///
+/// Understands plain ABI arrays, solc standard JSON output, Foundry `out/**/*.json` and
+/// Hardhat `artifacts/**/*.json`.
+#[derive(Clone, Default, Debug)]
+pub struct ArtifactIndex {
+    // Keys are selectors and topics in lowercase hex with `0x` prefix.
+    functions: HashMap<String, Vec<String>>,
+    errors: HashMap<String, Vec<String>>,
+    events: HashMap<String, Vec<EventSignature>>,
+}
+
+fn add_unique<T: PartialEq>(candidates: &mut Vec<T>, candidate: T) {
+    if !candidates.contains(&candidate) {
+        candidates.push(candidate);
+    }
+}
+
+/// Returns the canonical type of an ABI parameter, expanding tuples into their components.
+fn parameter_type(parameter: &Value) -> Option<String> {
+    let type_name = parameter["type"].as_str()?;
+    match type_name.strip_prefix("tuple") {
+        Some(array_suffix) => {
+            let components = parameter_types(parameter["components"].as_array()?)?;
+            Some(format!("({}){}", components, array_suffix))
+        }
+        None => Some(type_name.to_string()),
+    }
+}
+
+fn parameter_types(parameters: &[Value]) -> Option<String> {
+    Some(
+        parameters
+            .iter()
+            .map(parameter_type)
+            .collect::<Option<Vec<_>>>()?
+            .join(","),
+    )
+}
+
+fn entry_signature(entry: &Value) -> Option<String> {
+    let name = entry["name"].as_str()?;
+    let inputs = match entry["inputs"].as_array() {
+        Some(inputs) => parameter_types(inputs)?,
+        None => "".to_string(),
+    };
+    Some(format!("{}({})", name, inputs))
+}
+
+impl ArtifactIndex {
+    pub fn new() -> ArtifactIndex {
+        ArtifactIndex::default()
+    }
+
+    /// Adds all functions, errors and events from an ABI. Returns the number of entries added.
+    pub fn add_abi(&mut self, abi: &[Value]) -> usize {
+        let mut added = 0;
+        for entry in abi {
+            let signature = match entry_signature(entry) {
+                Some(signature) => signature,
+                None => continue,
+            };
+
+            match entry["type"].as_str() {
+                Some("function") => {
+                    let key = format_bytes(&selector(&signature));
+                    add_unique(self.functions.entry(key).or_default(), signature);
+                }
+                Some("error") => {
+                    let key = format_bytes(&selector(&signature));
+                    add_unique(self.errors.entry(key).or_default(), signature);
+                }
+                Some("event") => {
+                    let indexed_count = entry["inputs"].as_array().map_or(0, |inputs| {
+                        inputs
+                            .iter()
+                            .filter(|input| input["indexed"].as_bool() == Some(true))
+                            .count()
+                    });
+                    let key = format_bytes(&keccak256(signature.as_bytes()));
+                    let event = EventSignature {
+                        signature,
+                        indexed_count,
+                        anonymous: entry["anonymous"].as_bool() == Some(true),
+                    };
+                    add_unique(self.events.entry(key).or_default(), event);
+                }
+                _ => continue,
+            }
+            added += 1;
+        }
+        added
+    }
+
+    /// Adds ABIs from any of the supported JSON layouts. Returns the number of entries added.
+    pub fn add_json(&mut self, json: &Value) -> usize {
+        match json {
+            // Plain ABI.
+            Value::Array(abi) => self.add_abi(abi),
+            Value::Object(object) => {
+                if let Some(abi) = object.get("abi").and_then(Value::as_array) {
+                    // Foundry and Hardhat artifacts.
+                    self.add_abi(abi)
+                } else if let Some(sources) = object.get("contracts").and_then(Value::as_object) {
+                    // solc standard JSON output: contracts -> source file -> contract name.
+                    sources
+                        .values()
+                        .filter_map(Value::as_object)
+                        .flat_map(|contracts| contracts.values())
+                        .filter_map(|contract| contract["abi"].as_array())
+                        .map(|abi| self.add_abi(abi))
+                        .sum()
+                } else if let Some(output) = object.get("output") {
+                    // Build info files, which wrap the standard JSON output.
+                    self.add_json(output)
+                } else {
+                    0
+                }
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn load_file(&mut self, path: &Path) -> Result<usize> {
+        let json: Value = serde_json::from_str(&read_to_string(path)?)?;
+        Ok(self.add_json(&json))
+    }
+
+    /// Loads a JSON file or all JSON files in a directory tree. Paths that do not exist are
+    /// skipped. Returns errors for files that could not be read or parsed.
+    pub fn load_path(&mut self, path: &Path) -> Vec<(PathBuf, eyre::Report)> {
+        let mut errors = vec![];
+        if path.is_dir() {
+            let entries = match read_dir(path) {
+                Ok(entries) => entries,
+                Err(error) => return vec![(path.to_path_buf(), error.into())],
+            };
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                // Build info duplicates the artifacts and can be very large.
+                if name.starts_with('.') || name == "build-info" || name.ends_with(".dbg.json") {
+                    continue;
+                }
+                if entry_path.is_dir()
+                    || entry_path
+                        .extension()
+                        .map_or(false, |extension| extension == "json")
+                {
+                    errors.extend(self.load_path(&entry_path));
+                }
+            }
+        } else if path.is_file() {
+            if let Err(error) = self.load_file(path) {
+                errors.push((path.to_path_buf(), error));
+            }
+        }
+        errors
+    }
+
+    pub fn function_signatures(&self, selector: &str) -> Vec<String> {
+        self.functions
+            .get(&selector.to_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn error_signatures(&self, selector: &str) -> Vec<String> {
+        self.errors
+            .get(&selector.to_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn event_signatures(&self, topic: &str) -> Vec<EventSignature> {
+        self.events
+            .get(&topic.to_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn erc20_abi() -> Value {
+        json!([
+            {
+                "type": "function",
+                "name": "transfer",
+                "inputs": [
+                    {"name": "to", "type": "address"},
+                    {"name": "amount", "type": "uint256"}
+                ],
+                "outputs": [{"name": "", "type": "bool"}]
+            },
+            {
+                "type": "event",
+                "name": "Transfer",
+                "inputs": [
+                    {"name": "from", "type": "address", "indexed": true},
+                    {"name": "to", "type": "address", "indexed": true},
+                    {"name": "value", "type": "uint256", "indexed": false}
+                ],
+                "anonymous": false
+            },
+            {
+                "type": "error",
+                "name": "InsufficientBalance",
+                "inputs": [
+                    {
+                        "name": "details",
+                        "type": "tuple[]",
+                        "components": [
+                            {"name": "available", "type": "uint256"},
+                            {"name": "required", "type": "uint256"}
+                        ]
+                    }
+                ]
+            },
+            {"type": "constructor", "inputs": []}
+        ])
+    }
+
+    #[test]
+    fn artifact_layouts() {
+        let layouts = [
+            erc20_abi(),
+            json!({"abi": erc20_abi(), "bytecode": {"object": "0x"}}),
+            json!({"contracts": {"Token.sol": {"Token": {"abi": erc20_abi()}}}}),
+            json!({"output": {"contracts": {"Token.sol": {"Token": {"abi": erc20_abi()}}}}}),
+        ];
+
+        for layout in layouts {
+            let mut index = ArtifactIndex::new();
+            assert_eq!(index.add_json(&layout), 3);
+
+            assert_eq!(
+                index.function_signatures("0xa9059cbb"),
+                vec!["transfer(address,uint256)"]
+            );
+            assert_eq!(
+                index.event_signatures(
+                    "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef"
+                ),
+                vec![EventSignature {
+                    signature: "Transfer(address,address,uint256)".to_string(),
+                    indexed_count: 2,
+                    anonymous: false,
+                }]
+            );
+            assert_eq!(
+                index.error_signatures(&format_bytes(&selector(
+                    "InsufficientBalance((uint256,uint256)[])"
+                ))),
+                vec!["InsufficientBalance((uint256,uint256)[])"]
+            );
+        }
+    }
+}
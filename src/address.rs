@@ -0,0 +1,94 @@
+use crate::abi::keccak256;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ChecksumStatus {
+    Valid,
+    /// All letters have the same case, so the address carries no checksum.
+    Unchecked,
+    Invalid {
+        expected: String,
+    },
+}
+
+fn address_digits(address: &str) -> Option<&str> {
+    let digits = address.strip_prefix("0x")?;
+    if digits.len() == 40 && digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(digits)
+    } else {
+        None
+    }
+}
+
+pub fn is_address(text: &str) -> bool {
+    address_digits(text).is_some()
+}
+
+/// Converts a 20-byte address to its mixed-case EIP-55 form.
+pub fn to_checksum_address(address: &str) -> Option<String> {
+    let digits = address_digits(address)?.to_lowercase();
+    let hash = keccak256(digits.as_bytes());
+
+    let checksummed: String = digits
+        .chars()
+        .enumerate()
+        .map(|(index, c)| {
+            let nibble = (hash[index / 2] >> (if index % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect();
+    Some(format!("0x{}", checksummed))
+}
+
+pub fn checksum_status(address: &str) -> Option<ChecksumStatus> {
+    let digits = address_digits(address)?;
+    if digits == digits.to_lowercase() || digits == digits.to_uppercase() {
+        return Some(ChecksumStatus::Unchecked);
+    }
+
+    let expected = to_checksum_address(address)?;
+    if expected == address {
+        Some(ChecksumStatus::Valid)
+    } else {
+        Some(ChecksumStatus::Invalid { expected })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eip55_test_vectors() {
+        for address in [
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+            "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+            "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+            "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+        ] {
+            assert_eq!(
+                to_checksum_address(&address.to_lowercase()),
+                Some(address.to_string())
+            );
+            assert_eq!(checksum_status(address), Some(ChecksumStatus::Valid));
+        }
+    }
+
+    #[test]
+    fn invalid_checksum() {
+        assert_eq!(
+            checksum_status("0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"),
+            Some(ChecksumStatus::Invalid {
+                expected: "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".to_string()
+            })
+        );
+        assert_eq!(
+            checksum_status("0xe592427a0aece92de3edee1f18e0157c05861564"),
+            Some(ChecksumStatus::Unchecked)
+        );
+        assert_eq!(checksum_status("0x1234"), None);
+    }
+}
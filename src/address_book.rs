@@ -0,0 +1,297 @@
+use crate::address::is_address;
+use eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::{read_dir, read_to_string};
+use std::path::{Path, PathBuf};
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct AddressEntry {
+    pub name: String,
+    /// Chain the address was deployed to. `None` if it applies to every chain.
+    pub chain_id: Option<u64>,
+}
+
+/// Names of addresses deployed by the project itself or listed by the user.
+///
+/// Reads Foundry broadcasts (`broadcast/**/run-latest.json`), hardhat-deploy deployments
+/// (`deployments/<network>/*.json`) and `addresses.toml` files of the form:
+///
+/// ```toml
+/// # Names used on every chain.
+/// "0x000000000022D473030F116dDEE9F6B43aC78BA3" = "Permit2"
+///
+/// [1]
+/// "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" = "USDC"
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct AddressBook {
+    // Keys are addresses in lowercase hex with `0x` prefix.
+    entries: HashMap<String, Vec<AddressEntry>>,
+}
+
+/// Returns the chain id of a hardhat-deploy network directory, which hardhat-deploy stores in
+/// the `.chainId` file.
+fn hardhat_chain_id(directory: &Path) -> Option<u64> {
+    read_to_string(directory.join(".chainId"))
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Returns the chain id of a Foundry broadcast, which is also the name of its directory.
+fn broadcast_chain_id(json: &Value, path: &Path) -> Option<u64> {
+    json["chain"]
+        .as_u64()
+        .or_else(|| path.parent()?.file_name()?.to_string_lossy().parse().ok())
+}
+
+impl AddressBook {
+    pub fn new() -> AddressBook {
+        AddressBook::default()
+    }
+
+    /// Adds a named address. Returns false if it is not a valid address.
+    pub fn add(&mut self, address: &str, name: &str, chain_id: Option<u64>) -> bool {
+        if !is_address(address) || name.is_empty() {
+            return false;
+        }
+
+        let entry = AddressEntry {
+            name: name.to_string(),
+            chain_id,
+        };
+        let entries = self.entries.entry(address.to_lowercase()).or_default();
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+        true
+    }
+
+    /// Adds the contracts created by a Foundry script run.
+    pub fn add_broadcast(&mut self, json: &Value, chain_id: Option<u64>) -> usize {
+        let transactions = match json["transactions"].as_array() {
+            Some(transactions) => transactions,
+            None => return 0,
+        };
+        transactions
+            .iter()
+            .filter(|transaction| {
+                matches!(
+                    transaction["transactionType"].as_str(),
+                    Some("CREATE") | Some("CREATE2")
+                )
+            })
+            .filter(|transaction| {
+                match (
+                    transaction["contractAddress"].as_str(),
+                    transaction["contractName"].as_str(),
+                ) {
+                    (Some(address), Some(name)) => self.add(address, name, chain_id),
+                    _ => false,
+                }
+            })
+            .count()
+    }
+
+    /// Adds a hardhat-deploy deployment, which is named after its file.
+    pub fn add_deployment(&mut self, name: &str, json: &Value, chain_id: Option<u64>) -> usize {
+        match json["address"].as_str() {
+            Some(address) => self.add(address, name, chain_id) as usize,
+            None => 0,
+        }
+    }
+
+    /// Adds entries from an `addresses.toml` file. The whole file is validated first, so an
+    /// invalid entry leaves the book unchanged.
+    pub fn add_toml(&mut self, text: &str) -> Result<usize> {
+        let table = match text.parse::<toml::Value>()? {
+            toml::Value::Table(table) => table,
+            _ => return Err(eyre!("Expected a table")),
+        };
+
+        let mut entries = vec![];
+        for (key, value) in table {
+            match value {
+                toml::Value::String(name) if is_address(&key) && !name.is_empty() => {
+                    entries.push((key, name, None));
+                }
+                toml::Value::String(_) => return Err(eyre!("Invalid address {}", key)),
+                toml::Value::Table(chain) => {
+                    let chain_id = key.parse().map_err(|_| eyre!("Invalid chain id {}", key))?;
+                    for (address, name) in chain {
+                        match name {
+                            toml::Value::String(name)
+                                if is_address(&address) && !name.is_empty() =>
+                            {
+                                entries.push((address, name, Some(chain_id)));
+                            }
+                            _ => return Err(eyre!("Invalid entry for {}", address)),
+                        }
+                    }
+                }
+                _ => return Err(eyre!("Invalid entry for {}", key)),
+            }
+        }
+
+        for (address, name, chain_id) in &entries {
+            self.add(address, name, *chain_id);
+        }
+        Ok(entries.len())
+    }
+
+    /// Loads a file, detecting its format from its name and content.
+    pub fn load_file(&mut self, path: &Path) -> Result<usize> {
+        let content = read_to_string(path)?;
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "toml")
+        {
+            return self.add_toml(&content);
+        }
+
+        let json: Value = serde_json::from_str(&content)?;
+        if json.get("transactions").is_some() {
+            Ok(self.add_broadcast(&json, broadcast_chain_id(&json, path)))
+        } else {
+            let name = path
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let chain_id = path.parent().and_then(hardhat_chain_id);
+            Ok(self.add_deployment(&name, &json, chain_id))
+        }
+    }
+
+    /// Loads a file or all deployment files in a directory tree. Paths that do not exist are
+    /// skipped. Returns errors for files that could not be read or parsed.
+    pub fn load_path(&mut self, path: &Path) -> Vec<(PathBuf, eyre::Report)> {
+        let mut errors = vec![];
+        if path.is_dir() {
+            let entries = match read_dir(path) {
+                Ok(entries) => entries,
+                Err(error) => return vec![(path.to_path_buf(), error.into())],
+            };
+            for entry in entries.flatten() {
+                let entry_path = entry.path();
+                let name = entry.file_name().to_string_lossy().to_string();
+                // Simulated runs were never deployed. hardhat-deploy keeps compiler inputs
+                // next to the deployments.
+                if name.starts_with('.') || name == "dry-run" || name == "solcInputs" {
+                    continue;
+                }
+                let is_deployment = entry_path.extension().map_or(false, |extension| {
+                    extension == "json" && (!name.starts_with("run-") || name == "run-latest.json")
+                });
+                if entry_path.is_dir() || is_deployment {
+                    errors.extend(self.load_path(&entry_path));
+                }
+            }
+        } else if path.is_file() {
+            if let Err(error) = self.load_file(path) {
+                errors.push((path.to_path_buf(), error));
+            }
+        }
+        errors
+    }
+
+    /// Returns the names of an address. If a chain is given, names recorded for other chains
+    /// are left out.
+    pub fn lookup(&self, address: &str, chain_id: Option<u64>) -> Vec<AddressEntry> {
+        self.entries
+            .get(&address.to_lowercase())
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|entry| {
+                        chain_id.is_none() || entry.chain_id.is_none() || entry.chain_id == chain_id
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use serde_json::json;
+
+    const TOKEN: &str = "0x5fbdb2315678afecb367f032d93f642f64180aa3";
+    const USDC: &str = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+
+    #[test]
+    fn deployments() {
+        let mut book = AddressBook::new();
+        let broadcast = json!({
+            "transactions": [
+                {"transactionType": "CREATE", "contractName": "Token", "contractAddress": TOKEN},
+                {"transactionType": "CALL", "contractName": "Token", "contractAddress": USDC}
+            ],
+            "chain": 31337
+        });
+
+        assert_eq!(book.add_broadcast(&broadcast, Some(31337)), 1);
+        assert_eq!(
+            book.add_deployment("Vault", &json!({"address": TOKEN, "abi": []}), Some(1)),
+            1
+        );
+
+        assert_eq!(
+            book.lookup(&TOKEN.to_uppercase().replace("0X", "0x"), Some(31337)),
+            vec![AddressEntry {
+                name: "Token".to_string(),
+                chain_id: Some(31337)
+            }]
+        );
+        assert_eq!(book.lookup(TOKEN, None).len(), 2);
+        assert_eq!(book.lookup(USDC, None), vec![]);
+    }
+
+    #[test]
+    fn address_file() {
+        let mut book = AddressBook::new();
+        let text = indoc! {r#"
+            "0x000000000022D473030F116dDEE9F6B43aC78BA3" = "Permit2"
+
+            [1]
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" = "USDC"
+        "#};
+
+        assert_eq!(book.add_toml(text).unwrap(), 2);
+        assert_eq!(
+            book.lookup("0x000000000022d473030f116ddee9f6b43ac78ba3", Some(10)),
+            vec![AddressEntry {
+                name: "Permit2".to_string(),
+                chain_id: None
+            }]
+        );
+        assert_eq!(book.lookup(USDC, Some(10)), vec![]);
+        assert_eq!(book.lookup(USDC, Some(1)).len(), 1);
+
+        assert!(book.add_toml("\"0x1234\" = \"Short\"").is_err());
+        assert!(book.add_toml("[mainnet]\n").is_err());
+    }
+
+    #[test]
+    fn invalid_address_file_adds_nothing() {
+        let mut book = AddressBook::new();
+        let text = indoc! {r#"
+            [1]
+            "0x000000000022D473030F116dDEE9F6B43aC78BA3" = "Permit2"
+            "0x1234" = "Short"
+            "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48" = "USDC"
+        "#};
+
+        assert!(book.add_toml(text).is_err());
+        assert_eq!(
+            book.lookup("0x000000000022d473030f116ddee9f6b43ac78ba3", None),
+            vec![]
+        );
+        assert_eq!(book.lookup(USDC, None), vec![]);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::resolver::ResolverKind;
+use eyre::Result;
+use serde::Deserialize;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Settings passed by the editor in `initializationOptions`, on top of those of the user config
+/// file.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Additional signature files. JSON files or text files with one signature per line.
+    pub signature_files: Vec<PathBuf>,
+    /// ABI JSON files, compiler output and build artifact directories. Directories are
+    /// searched recursively for JSON files.
+    pub artifact_paths: Vec<PathBuf>,
+    /// Foundry broadcast directories, hardhat-deploy deployment directories and
+    /// `addresses.toml` files used to name addresses.
+    pub address_book_paths: Vec<PathBuf>,
+    /// Chain whose deployments are used to name addresses. All chains are used if not set.
+    pub chain_id: Option<u64>,
+    /// Sources of names, in the order in which they are asked. The first one that knows a
+    /// value wins.
+    pub resolvers: Vec<ResolverKind>,
+    pub dune: DuneSettings,
+    /// Stores names found remotely on disk, so that they survive restarts.
+    pub persistent_cache: bool,
+    /// Cache file. Defaults to `$XDG_CACHE_HOME/yul-lsp/names.json`.
+    pub cache_file: Option<PathBuf>,
+    /// ERC-7201 namespace ids whose storage roots are recognized, e.g. `example.main`.
+    pub storage_namespaces: Vec<String>,
+}
+
+/// Dune connection settings. Unset values are taken from the environment, see
+/// `DuneConfig::load`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DuneSettings {
+    pub api_url: Option<String>,
+    pub api_key: Option<String>,
+    /// File with `KEY=value` lines providing the same variables as the environment.
+    pub env_file: PathBuf,
+    pub function_signature_query_id: Option<i32>,
+    pub contract_name_query_id: Option<i32>,
+    pub function_signature_batch_query_id: Option<i32>,
+    pub contract_name_batch_query_id: Option<i32>,
+}
+
+impl Default for DuneSettings {
+    fn default() -> DuneSettings {
+        DuneSettings {
+            api_url: None,
+            api_key: None,
+            env_file: PathBuf::from(".env"),
+            function_signature_query_id: None,
+            contract_name_query_id: None,
+            function_signature_batch_query_id: None,
+            contract_name_batch_query_id: None,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            signature_files: vec![],
+            // Default output directories of Foundry and Hardhat.
+            artifact_paths: vec![PathBuf::from("out"), PathBuf::from("artifacts")],
+            address_book_paths: vec![
+                PathBuf::from("broadcast"),
+                PathBuf::from("deployments"),
+                PathBuf::from("addresses.toml"),
+            ],
+            chain_id: None,
+            resolvers: ResolverKind::default_order(),
+            dune: DuneSettings::default(),
+            persistent_cache: true,
+            cache_file: None,
+            storage_namespaces: vec![],
+        }
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME/yul-lsp/config.toml`, falling back to `~/.config`.
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|config_home| !config_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+    Some(config_home.join("yul-lsp").join("config.toml"))
+}
+
+/// Settings holding a path or a list of paths, as JSON pointers.
+const PATH_SETTINGS: [&str; 5] = [
+    "/signatureFiles",
+    "/artifactPaths",
+    "/addressBookPaths",
+    "/dune/envFile",
+    "/cacheFile",
+];
+
+/// Makes relative paths of the settings relative to the directory. Paths of the user config
+/// file are meant relative to that file, not to the workspace.
+fn resolve_value_paths(settings: &mut Value, directory: &Path) {
+    for pointer in PATH_SETTINGS {
+        let paths = match settings.pointer_mut(pointer) {
+            Some(Value::Array(paths)) => paths.iter_mut().collect(),
+            Some(path) => vec![path],
+            None => continue,
+        };
+        for path in paths {
+            if let Value::String(path) = path {
+                if Path::new(path.as_str()).is_relative() {
+                    *path = directory.join(&*path).to_string_lossy().to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Merges objects key by key. Other values are replaced.
+fn merge_values(base: &mut Value, overrides: Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                merge_values(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overrides) => *base = overrides,
+    }
+}
+
+impl Settings {
+    /// Combines the initialization options with the user config file, given as its path and
+    /// content. The file has the same keys in TOML. Initialization options take precedence.
+    pub fn from_sources(
+        options: Option<Value>,
+        config_file: Option<(&Path, &str)>,
+    ) -> Result<Settings> {
+        let mut settings = match config_file {
+            Some((path, content)) => {
+                let mut settings = toml::from_str::<Value>(content)?;
+                if let Some(directory) = path.parent() {
+                    resolve_value_paths(&mut settings, directory);
+                }
+                settings
+            }
+            None => Value::Object(Default::default()),
+        };
+        if let Some(options) = options {
+            merge_values(&mut settings, options);
+        }
+        Ok(serde_json::from_value(settings)?)
+    }
+
+    /// Makes relative paths relative to the workspace root rather than the working directory
+    /// of the server, which depends on how the editor launches it.
+    pub fn resolve_paths(&mut self, root: &Path) {
+        for path in self
+            .signature_files
+            .iter_mut()
+            .chain(self.artifact_paths.iter_mut())
+            .chain(self.address_book_paths.iter_mut())
+            .chain(std::iter::once(&mut self.dune.env_file))
+            .chain(self.cache_file.iter_mut())
+        {
+            if path.is_relative() {
+                *path = root.join(&*path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use serde_json::json;
+
+    #[test]
+    fn initialization_options() {
+        let mut settings = Settings::from_sources(
+            Some(json!({
+                "signatureFiles": ["signatures.txt", "/abs/signatures.json"],
+                "artifactPaths": ["build/Token.json"],
+                "chainId": 1,
+                "resolvers": ["dune", "signatureDatabase"],
+                "dune": {"apiUrl": "http://localhost:8080", "contractNameQueryId": 42},
+                "storageNamespaces": ["example.main"]
+            })),
+            None,
+        )
+        .unwrap();
+        settings.resolve_paths(Path::new("/workspace"));
+
+        assert_eq!(
+            settings.signature_files,
+            vec![
+                PathBuf::from("/workspace/signatures.txt"),
+                PathBuf::from("/abs/signatures.json")
+            ]
+        );
+        assert_eq!(
+            settings.artifact_paths,
+            vec![PathBuf::from("/workspace/build/Token.json")]
+        );
+        assert_eq!(settings.chain_id, Some(1));
+        assert_eq!(
+            settings.resolvers,
+            vec![ResolverKind::Dune, ResolverKind::SignatureDatabase]
+        );
+        assert_eq!(
+            settings.address_book_paths[2],
+            PathBuf::from("/workspace/addresses.toml")
+        );
+        assert_eq!(
+            settings.dune,
+            DuneSettings {
+                api_url: Some("http://localhost:8080".to_string()),
+                env_file: PathBuf::from("/workspace/.env"),
+                contract_name_query_id: Some(42),
+                ..DuneSettings::default()
+            }
+        );
+        assert_eq!(
+            settings.storage_namespaces,
+            vec!["example.main".to_string()]
+        );
+        assert_eq!(
+            Settings::from_sources(None, None).unwrap(),
+            Settings::default()
+        );
+    }
+
+    #[test]
+    fn config_file() {
+        let config_file = indoc! {r#"
+            chainId = 10
+            signatureFiles = ["signatures.txt", "/abs/signatures.json"]
+
+            [dune]
+            apiKey = "from-config"
+            contractNameQueryId = 42
+            envFile = "dune.env"
+        "#};
+        let path = Path::new("/home/user/.config/yul-lsp/config.toml");
+        let mut settings = Settings::from_sources(
+            Some(json!({"dune": {"apiKey": "from-options"}, "cacheFile": "names.json"})),
+            Some((path, config_file)),
+        )
+        .unwrap();
+        settings.resolve_paths(Path::new("/workspace"));
+
+        assert_eq!(settings.chain_id, Some(10));
+        assert_eq!(settings.dune.api_key, Some("from-options".to_string()));
+        assert_eq!(settings.dune.contract_name_query_id, Some(42));
+        // Paths of the config file are relative to it, those of the options to the workspace.
+        assert_eq!(
+            settings.signature_files,
+            vec![
+                PathBuf::from("/home/user/.config/yul-lsp/signatures.txt"),
+                PathBuf::from("/abs/signatures.json")
+            ]
+        );
+        assert_eq!(
+            settings.dune.env_file,
+            PathBuf::from("/home/user/.config/yul-lsp/dune.env")
+        );
+        assert_eq!(
+            settings.cache_file,
+            Some(PathBuf::from("/workspace/names.json"))
+        );
+        assert!(Settings::from_sources(None, Some((path, "chainId = "))).is_err());
+    }
+}
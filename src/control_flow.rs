@@ -0,0 +1,364 @@
+use crate::literal_value::evaluate_literal;
+use std::collections::HashSet;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{Block, Expression, FunctionDefinition, IdentifierID, Statement};
+
+/// Built-ins that end the execution of the whole call frame.
+pub const TERMINATING_BUILTINS: [&str; 5] = ["revert", "return", "stop", "invalid", "selfdestruct"];
+
+pub type BlockId = usize;
+
+/// A straight-line sequence of statements. Control enters only at the first statement and
+/// leaves only after the last one.
+///
+/// Compound statements (`if`, `switch`, `for`) belong to the block that evaluates their
+/// condition. Their bodies are placed in separate blocks. Function definitions are not
+/// executed where they appear and are not part of any block.
+#[derive(Debug, Default)]
+pub struct BasicBlock<'a> {
+    pub statements: Vec<&'a Statement>,
+    pub successors: Vec<BlockId>,
+}
+
+/// Control-flow graph of a single function body or of the top-level code.
+#[derive(Debug)]
+pub struct ControlFlowGraph<'a> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub entry: BlockId,
+    /// Empty block representing a normal return from the function, i.e. reaching the end of
+    /// its body or executing `leave`.
+    pub exit: BlockId,
+}
+
+struct LoopTargets {
+    pub continue_target: BlockId,
+    pub break_target: BlockId,
+}
+
+struct Builder<'a, 'b> {
+    pub blocks: Vec<BasicBlock<'a>>,
+    pub exit: BlockId,
+    pub loops: Vec<LoopTargets>,
+    pub terminating_functions: &'b HashSet<u64>,
+}
+
+impl<'a, 'b> Builder<'a, 'b> {
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(BasicBlock::default());
+        self.blocks.len() - 1
+    }
+
+    fn add_edge(&mut self, from: BlockId, to: BlockId) {
+        self.blocks[from].successors.push(to);
+    }
+
+    fn terminates(&self, expression: &Expression) -> bool {
+        match expression {
+            Expression::FunctionCall(function_call) => {
+                let callee_terminates = match function_call.function.id {
+                    IdentifierID::Reference(id) => self.terminating_functions.contains(&id),
+                    _ => TERMINATING_BUILTINS.contains(&function_call.function.name.as_str()),
+                };
+                callee_terminates
+                    || function_call
+                        .arguments
+                        .iter()
+                        .any(|argument| self.terminates(argument))
+            }
+            _ => false,
+        }
+    }
+
+    /// Called after a statement that stops execution, e.g. `revert()`, which leaves the current
+    /// block without successors. Returns a new block without predecessors, which will receive
+    /// the unreachable statements that follow, if any.
+    fn halt(&mut self) -> BlockId {
+        self.new_block()
+    }
+
+    fn jump(&mut self, current: BlockId, target: Option<BlockId>) -> BlockId {
+        if let Some(target) = target {
+            self.add_edge(current, target);
+        }
+        self.new_block()
+    }
+
+    /// Adds statements to the graph, starting in block `current`. Returns the block in which
+    /// control ends up after the last statement.
+    fn add_statements(&mut self, statements: &'a [Statement], mut current: BlockId) -> BlockId {
+        for statement in statements {
+            current = self.add_statement(statement, current);
+        }
+        current
+    }
+
+    fn add_statement(&mut self, statement: &'a Statement, current: BlockId) -> BlockId {
+        if let Statement::FunctionDefinition(_) = statement {
+            return current;
+        }
+        self.blocks[current].statements.push(statement);
+
+        match statement {
+            Statement::Expression(expression) if self.terminates(expression) => self.halt(),
+            Statement::VariableDeclaration(declaration) => match &declaration.value {
+                Some(value) if self.terminates(value) => self.halt(),
+                _ => current,
+            },
+            Statement::Assignment(assignment) if self.terminates(&assignment.value) => self.halt(),
+            Statement::Break => {
+                let target = self.loops.last().map(|targets| targets.break_target);
+                self.jump(current, target)
+            }
+            Statement::Continue => {
+                let target = self.loops.last().map(|targets| targets.continue_target);
+                self.jump(current, target)
+            }
+            Statement::Leave => self.jump(current, Some(self.exit)),
+            Statement::Block(block) => {
+                let body = self.new_block();
+                self.add_edge(current, body);
+                let body_end = self.add_statements(&block.statements, body);
+                let after = self.new_block();
+                self.add_edge(body_end, after);
+                after
+            }
+            Statement::If(if_statement) => {
+                if self.terminates(&if_statement.condition) {
+                    return self.halt();
+                }
+                let body = self.new_block();
+                self.add_edge(current, body);
+                let body_end = self.add_statements(&if_statement.body.statements, body);
+                let after = self.new_block();
+                self.add_edge(current, after);
+                self.add_edge(body_end, after);
+                after
+            }
+            Statement::Switch(switch) => {
+                if self.terminates(&switch.expression) {
+                    return self.halt();
+                }
+                let after = self.new_block();
+                for case in &switch.cases {
+                    let body = self.new_block();
+                    self.add_edge(current, body);
+                    let body_end = self.add_statements(&case.body.statements, body);
+                    self.add_edge(body_end, after);
+                }
+                if !switch.cases.iter().any(|case| case.literal.is_none()) {
+                    self.add_edge(current, after);
+                }
+                after
+            }
+            Statement::ForLoop(for_loop) => {
+                // The init block is executed once, in the scope of the loop.
+                let pre_end = self.add_statements(&for_loop.pre.statements, current);
+                let condition = self.new_block();
+                self.add_edge(pre_end, condition);
+                if self.terminates(&for_loop.condition) {
+                    return self.halt();
+                }
+
+                let body = self.new_block();
+                let post = self.new_block();
+                let after = self.new_block();
+                self.add_edge(condition, body);
+                if !is_nonzero_constant(&for_loop.condition) {
+                    self.add_edge(condition, after);
+                }
+
+                self.loops.push(LoopTargets {
+                    continue_target: post,
+                    break_target: after,
+                });
+                let body_end = self.add_statements(&for_loop.body.statements, body);
+                self.loops.pop();
+                self.add_edge(body_end, post);
+                let post_end = self.add_statements(&for_loop.post.statements, post);
+                self.add_edge(post_end, condition);
+                after
+            }
+            _ => current,
+        }
+    }
+}
+
+fn is_nonzero_constant(expression: &Expression) -> bool {
+    match expression {
+        Expression::Literal(literal) => {
+            matches!(evaluate_literal(&literal.literal), Ok(value) if !value.is_zero())
+        }
+        _ => false,
+    }
+}
+
+impl<'a> ControlFlowGraph<'a> {
+    /// Builds the graph of a function body or the top-level block.
+    ///
+    /// `terminating_functions` contains ids of user-defined functions known to never return
+    /// normally. Calls to them are treated like calls to `revert()`.
+    pub fn build(body: &'a Block, terminating_functions: &HashSet<u64>) -> ControlFlowGraph<'a> {
+        let mut builder = Builder {
+            blocks: vec![],
+            exit: 0,
+            loops: vec![],
+            terminating_functions,
+        };
+        builder.exit = builder.new_block();
+        let entry = builder.new_block();
+        let body_end = builder.add_statements(&body.statements, entry);
+        builder.add_edge(body_end, builder.exit);
+
+        ControlFlowGraph {
+            blocks: builder.blocks,
+            entry,
+            exit: builder.exit,
+        }
+    }
+
+    /// Returns a flag for every block, telling whether it can be reached from the entry.
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.blocks.len()];
+        let mut pending = vec![self.entry];
+        while let Some(block) = pending.pop() {
+            if !reachable[block] {
+                reachable[block] = true;
+                pending.extend(self.blocks[block].successors.iter().copied());
+            }
+        }
+        reachable
+    }
+
+    /// Tells whether the function can return normally.
+    pub fn returns(&self) -> bool {
+        self.reachable()[self.exit]
+    }
+}
+
+struct FunctionCollector<'a> {
+    pub functions: Vec<&'a FunctionDefinition>,
+}
+
+impl<'a> FunctionCollector<'a> {
+    // Not using ASTVisitor because it does not give out references that outlive the visit.
+    fn collect_block(&mut self, block: &'a Block) {
+        for statement in &block.statements {
+            match statement {
+                Statement::FunctionDefinition(function) => {
+                    self.functions.push(function);
+                    self.collect_block(&function.body);
+                }
+                Statement::Block(block) => self.collect_block(block),
+                Statement::If(if_statement) => self.collect_block(&if_statement.body),
+                Statement::Switch(switch) => {
+                    for case in &switch.cases {
+                        self.collect_block(&case.body);
+                    }
+                }
+                Statement::ForLoop(for_loop) => {
+                    self.collect_block(&for_loop.pre);
+                    self.collect_block(&for_loop.body);
+                    self.collect_block(&for_loop.post);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Returns all function definitions in the AST, including nested ones.
+pub fn collect_functions(ast: &Block) -> Vec<&FunctionDefinition> {
+    let mut collector = FunctionCollector { functions: vec![] };
+    collector.collect_block(ast);
+    collector.functions
+}
+
+/// Finds user-defined functions that never return normally, e.g. because all paths through
+/// them end in `revert()` or in a call to another such function.
+pub fn find_terminating_functions(ast: &Block) -> HashSet<u64> {
+    let functions = collect_functions(ast);
+    let mut terminating = HashSet::new();
+
+    // A function found to be terminating can make its callers terminating too.
+    loop {
+        let mut changed = false;
+        for function in &functions {
+            if let IdentifierID::Declaration(id) = function.name.id {
+                if !terminating.contains(&id)
+                    && !ControlFlowGraph::build(&function.body, &terminating).returns()
+                {
+                    terminating.insert(id);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return terminating;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+    use yultsur::dialect::EVMDialect;
+    use yultsur::resolver::resolve;
+    use yultsur::yul_parser::parse_block;
+
+    fn terminating_function_names(source_code: &str) -> Vec<String> {
+        let mut ast = parse_block(source_code).unwrap();
+        resolve::<EVMDialect>(&mut ast);
+        let terminating = find_terminating_functions(&ast);
+
+        collect_functions(&ast)
+            .into_iter()
+            .filter(|function| match function.name.id {
+                IdentifierID::Declaration(id) => terminating.contains(&id),
+                _ => false,
+            })
+            .map(|function| function.name.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn erc20_terminating_functions() {
+        let source_code = read_to_string("examples/erc20.yul").unwrap();
+
+        assert_eq!(
+            terminating_function_names(&source_code),
+            vec!["returnUint", "returnTrue"]
+        );
+    }
+
+    #[test]
+    fn loops_and_leave() {
+        let source_code = indoc! {"
+            {
+                function loopForever() {
+                    for {} 1 {} {}
+                }
+                function loopUntilBreak() {
+                    for {} 1 {} { break }
+                }
+                function loopNever() {
+                    for {} false {} {}
+                }
+                function leaveEarly() {
+                    leave
+                    revert(0, 0)
+                }
+                function callTerminating() {
+                    if 1 { stop() }
+                    loopForever()
+                }
+            }
+        "};
+
+        assert_eq!(
+            terminating_function_names(source_code),
+            vec!["loopForever", "callTerminating"]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::dispatcher::{check_dispatchers, SignatureLookup};
+use crate::event_checker::{check_events, EventLookup};
+use crate::literal_checker::check_literals;
+use crate::scope_checker::check_scopes;
+use crate::storage_slots::check_storage_slots;
+use crate::unreachable_checker::check_unreachable;
+use crate::unused_checker::check_unused;
+use yultsur::yul::{Block, SourceLocation};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RelatedLocation {
+    pub location: SourceLocation,
+    pub message: String,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Edit {
+    pub location: SourceLocation,
+    pub replacement: String,
+}
+
+/// A quick fix offered to the user as a code action.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fix {
+    pub title: String,
+    pub edits: Vec<Edit>,
+}
+
+/// A problem found by one of the analysis passes, expressed in terms of byte offsets in the
+/// source. Conversion to LSP diagnostics happens in the server, which knows the document.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Finding {
+    pub location: SourceLocation,
+    pub severity: Severity,
+    pub message: String,
+    pub related: Vec<RelatedLocation>,
+    /// Marks code that can be removed. Editors usually render it faded out.
+    pub unnecessary: bool,
+    pub fix: Option<Fix>,
+}
+
+impl Finding {
+    pub fn error(location: SourceLocation, message: String) -> Finding {
+        Finding {
+            location,
+            severity: Severity::Error,
+            message,
+            related: vec![],
+            unnecessary: false,
+            fix: None,
+        }
+    }
+
+    pub fn warning(location: SourceLocation, message: String) -> Finding {
+        Finding {
+            location,
+            severity: Severity::Warning,
+            message,
+            related: vec![],
+            unnecessary: false,
+            fix: None,
+        }
+    }
+
+    pub fn with_related(mut self, location: SourceLocation, message: String) -> Finding {
+        self.related.push(RelatedLocation { location, message });
+        self
+    }
+
+    pub fn unnecessary(mut self) -> Finding {
+        self.unnecessary = true;
+        self
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Finding {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// Runs all analysis passes on an AST that has already gone through the resolver.
+///
+/// `known_signatures` and `known_events` are used to look up signatures of selectors and
+/// events of topics, if a signature source is available.
+pub fn analyze(
+    source: &str,
+    ast: &Block,
+    known_signatures: Option<SignatureLookup>,
+    known_events: Option<EventLookup>,
+) -> Vec<Finding> {
+    let mut findings = check_scopes(ast);
+    findings.extend(check_unused(source, ast));
+    findings.extend(check_unreachable(ast));
+    findings.extend(check_literals(ast));
+    findings.extend(check_dispatchers(source, ast, known_signatures));
+    findings.extend(check_events(ast, known_events));
+    findings.extend(check_storage_slots(source, ast));
+    findings
+}
@@ -0,0 +1,248 @@
+use crate::abi::{find_signature, format_bytes, selector};
+use crate::diagnostics::Finding;
+use crate::literal_value::{evaluate_literal, U256};
+use std::collections::HashMap;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{Block, Expression, FunctionCall, Literal, SourceLocation, Switch};
+
+/// Returns the candidate signatures known for a selector in `0x` + 8 hex digits form.
+pub type SignatureLookup<'a> = &'a dyn Fn(&str) -> Vec<String>;
+
+pub fn is_call_to(expression: &Expression, name: &str) -> Option<&FunctionCall> {
+    match expression {
+        Expression::FunctionCall(function_call) if function_call.function.name == name => {
+            Some(function_call)
+        }
+        _ => None,
+    }
+}
+
+pub fn is_literal_value(expression: &Expression, value: U256) -> bool {
+    match expression {
+        Expression::Literal(literal) => evaluate_literal(&literal.literal) == Ok(value),
+        _ => false,
+    }
+}
+
+/// Matches `calldataload(0)`.
+fn is_first_calldata_word(expression: &Expression) -> bool {
+    match is_call_to(expression, "calldataload") {
+        Some(function_call) => {
+            function_call.arguments.len() == 1
+                && is_literal_value(&function_call.arguments[0], U256::default())
+        }
+        None => false,
+    }
+}
+
+pub fn is_selector_expression(expression: &Expression) -> bool {
+    let mut shift_224 = U256::default();
+    shift_224.0[3] = 1;
+
+    if let Some(function_call) = is_call_to(expression, "shr") {
+        // shr(224, calldataload(0))
+        return function_call.arguments.len() == 2
+            && is_literal_value(&function_call.arguments[0], U256::from_u64(224))
+            && is_first_calldata_word(&function_call.arguments[1]);
+    }
+    if let Some(function_call) = is_call_to(expression, "div") {
+        // div(calldataload(0), 0x100000000000000000000000000000000000000000000000000000000)
+        return function_call.arguments.len() == 2
+            && is_first_calldata_word(&function_call.arguments[0])
+            && is_literal_value(&function_call.arguments[1], shift_224);
+    }
+    match expression {
+        // A helper like `selector()` in examples/erc20.yul.
+        Expression::FunctionCall(function_call) => {
+            function_call.arguments.is_empty()
+                && function_call
+                    .function
+                    .name
+                    .to_lowercase()
+                    .contains("selector")
+        }
+        _ => false,
+    }
+}
+
+/// Tells whether the switch dispatches calls based on the function selector, i.e. looks like
+/// `switch selector()` or `switch shr(224, calldataload(0))`.
+pub fn is_dispatcher(switch: &Switch) -> bool {
+    is_selector_expression(&switch.expression)
+}
+
+/// Returns the selector a `case` label compares against, provided that it fits in 4 bytes.
+pub fn case_selector(literal: &Literal) -> Option<String> {
+    let value = evaluate_literal(&literal.literal).ok()?;
+    if value.bit_length() > 32 {
+        return None;
+    }
+    Some(format_bytes(&value.0[28..]))
+}
+
+/// Returns the text of a comment following the literal on the same line or occupying the
+/// whole preceding line.
+fn neighbouring_comment<'s>(source: &'s str, location: &SourceLocation) -> Option<&'s str> {
+    let line_start = source[..location.start]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let line_end = source[location.end..]
+        .find('\n')
+        .map_or(source.len(), |newline| location.end + newline);
+
+    let rest_of_line = &source[location.end..line_end];
+    if let Some(comment_start) = rest_of_line.find("//").or_else(|| rest_of_line.find("/*")) {
+        return Some(&rest_of_line[comment_start + 2..]);
+    }
+
+    if line_start == 0 {
+        return None;
+    }
+    let previous_line_start = source[..line_start - 1]
+        .rfind('\n')
+        .map_or(0, |newline| newline + 1);
+    let previous_line = source[previous_line_start..line_start - 1].trim();
+    previous_line
+        .strip_prefix("//")
+        .or_else(|| previous_line.strip_prefix("/*"))
+}
+
+struct DispatcherChecker<'a> {
+    pub source: &'a str,
+    pub known_signatures: Option<SignatureLookup<'a>>,
+    pub findings: Vec<Finding>,
+}
+
+impl<'a> DispatcherChecker<'a> {
+    fn check_case(&mut self, literal: &Literal, case_selector: &str, location: &SourceLocation) {
+        let named_signature = neighbouring_comment(self.source, location).and_then(find_signature);
+
+        if let Some(signature) = &named_signature {
+            let expected_selector = format_bytes(&selector(signature));
+            if expected_selector != case_selector {
+                self.findings.push(Finding::warning(
+                    location.clone(),
+                    format!(
+                        "Selector {} does not match '{}' from the comment, whose selector is {}.",
+                        literal.literal, signature, expected_selector
+                    ),
+                ));
+            }
+        }
+
+        if let Some(known_signatures) = self.known_signatures {
+            let candidates = known_signatures(case_selector);
+            if candidates.len() > 1 {
+                self.findings.push(Finding::warning(
+                    location.clone(),
+                    format!(
+                        "Selector {} is shared by multiple known signatures: {}.",
+                        literal.literal,
+                        candidates.join(", ")
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+impl<'a> ASTVisitor for DispatcherChecker<'a> {
+    fn visit_switch(&mut self, switch: &Switch) {
+        if is_dispatcher(switch) {
+            let mut seen_selectors: HashMap<String, SourceLocation> = HashMap::new();
+            for literal in switch.cases.iter().filter_map(|case| case.literal.as_ref()) {
+                let (case_selector, location) = match (case_selector(literal), &literal.location) {
+                    (Some(case_selector), Some(location)) => (case_selector, location),
+                    _ => continue,
+                };
+
+                match seen_selectors.get(&case_selector) {
+                    Some(previous_location) => self.findings.push(
+                        Finding::error(
+                            location.clone(),
+                            format!("Duplicate selector {} in dispatcher.", case_selector),
+                        )
+                        .with_related(
+                            previous_location.clone(),
+                            "The selector is first handled here.".to_string(),
+                        ),
+                    ),
+                    None => {
+                        self.check_case(literal, &case_selector, location);
+                        seen_selectors.insert(case_selector, location.clone());
+                    }
+                }
+            }
+        }
+
+        self.visit_expression(&switch.expression);
+        for case in &switch.cases {
+            self.visit_case(case);
+        }
+    }
+}
+
+/// Checks selector dispatchers for duplicate selectors, selectors not matching the signature
+/// named in a neighbouring comment and, if a signature source is available, selectors that
+/// are ambiguous.
+pub fn check_dispatchers(
+    source: &str,
+    ast: &Block,
+    known_signatures: Option<SignatureLookup>,
+) -> Vec<Finding> {
+    let mut dispatcher_checker = DispatcherChecker {
+        source,
+        known_signatures,
+        findings: vec![],
+    };
+    dispatcher_checker.visit_block(ast);
+    dispatcher_checker.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+    use yultsur::yul_parser::parse_block;
+
+    #[test]
+    fn erc20_dispatcher() {
+        let source_code = read_to_string("examples/erc20.yul").unwrap();
+        let ast = parse_block(&source_code).unwrap();
+
+        assert_eq!(check_dispatchers(&source_code, &ast, None), vec![]);
+    }
+
+    #[test]
+    fn duplicate_and_mismatched_selectors() {
+        let source_code = indoc! {"
+            {
+                switch shr(224, calldataload(0))
+                case 0xa9059cbb { } // transfer(address to, uint256 amount)
+                // balanceOf(address)
+                case 0x18160ddd { }
+                case 0xa9059cbb { }
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let lookup = |selector: &str| match selector {
+            "0x18160ddd" => vec!["totalSupply()".to_string(), "other()".to_string()],
+            _ => vec![],
+        };
+        let findings = check_dispatchers(source_code, &ast, Some(&lookup as SignatureLookup));
+        let messages: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Selector 0x18160ddd does not match 'balanceOf(address)' from the comment, whose selector is 0x70a08231.",
+                "Selector 0x18160ddd is shared by multiple known signatures: totalSupply(), other().",
+                "Duplicate selector 0xa9059cbb in dispatcher.",
+            ]
+        );
+    }
+}
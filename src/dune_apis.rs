@@ -1,173 +1,795 @@
-use eyre::Result;
-use lazy_static::lazy_static;
-use reqwest::Client;
+use crate::config::DuneSettings;
+use futures::stream::{self, StreamExt};
 use reqwest::{self};
-use serde_json::Value;
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::read_to_string;
-use std::{thread, time};
+use std::future::Future;
+use std::time::{Duration, Instant};
 
-pub static QUERY_FUNCTION_NAME_SIGNATURE: i32 = 1279121;
-pub static QUERY_CONTRACT_NAME: i32 = 1279874;
+pub const DEFAULT_API_URL: &str = "https://api.dune.com/api/v1";
+pub const DEFAULT_FUNCTION_SIGNATURE_QUERY_ID: i32 = 1279121;
+pub const DEFAULT_CONTRACT_NAME_QUERY_ID: i32 = 1279874;
 
-lazy_static! {
-    static ref DUNE_API_KEY: String = get_dune_api_key().unwrap();
+/// How long to wait for an execution to complete before giving up on it.
+const EXECUTION_TIMEOUT: Duration = Duration::from_secs(60);
+/// Delay before the first status check. Doubled after every check up to the maximum.
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(4);
+/// Limit of executions started at once when keys are looked up one by one.
+const MAX_CONCURRENT_EXECUTIONS: usize = 4;
+
+#[derive(Debug)]
+pub enum DuneError {
+    MissingApiKey,
+    Http(reqwest::Error),
+    /// Dune rejected the request, e.g. because of an invalid API key.
+    Api {
+        status: u16,
+        message: String,
+    },
+    InvalidResponse(String),
+    /// The execution did not complete within the timeout.
+    Pending {
+        execution_id: String,
+    },
+    Failed {
+        execution_id: String,
+        state: String,
+    },
+    Cancelled {
+        execution_id: String,
+    },
 }
 
-pub async fn get_function_name(
-    client: &Client,
-    query_id: i32,
-    function_signature: String,
-) -> Result<String> {
-    // TODO: (fix) figure out better way than "replace"
-    let body = r#"{
-        "query_parameters": {
-            "query_id":"function_signature"
+impl fmt::Display for DuneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DuneError::MissingApiKey => write!(f, "No Dune API key is configured"),
+            DuneError::Http(error) => write!(f, "Request to Dune failed: {}", error),
+            DuneError::Api { status, message } => {
+                write!(f, "Dune returned status {}: {}", status, message)
+            }
+            DuneError::InvalidResponse(message) => {
+                write!(f, "Unexpected response from Dune: {}", message)
+            }
+            DuneError::Pending { execution_id } => write!(
+                f,
+                "Execution {} did not complete within {} seconds",
+                execution_id,
+                EXECUTION_TIMEOUT.as_secs()
+            ),
+            DuneError::Failed {
+                execution_id,
+                state,
+            } => write!(f, "Execution {} ended in state {}", execution_id, state),
+            DuneError::Cancelled { execution_id } => {
+                write!(f, "Execution {} was cancelled", execution_id)
+            }
         }
-      }"#
-    .replace("function_signature", &function_signature);
+    }
+}
 
-    // Execute query
-    let query_execution: String = execute_query(client, query_id, body).await?;
+impl std::error::Error for DuneError {}
 
-    let query_execution_object: Value = query_execution.try_into()?;
+impl From<reqwest::Error> for DuneError {
+    fn from(error: reqwest::Error) -> DuneError {
+        DuneError::Http(error)
+    }
+}
+
+impl From<serde_json::Error> for DuneError {
+    fn from(error: serde_json::Error) -> DuneError {
+        DuneError::InvalidResponse(error.to_string())
+    }
+}
+
+/// Where and how to query Dune.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct DuneConfig {
+    pub api_url: String,
+    pub api_key: Option<String>,
+    pub function_signature_query_id: i32,
+    pub contract_name_query_id: i32,
+    /// Queries taking a comma-separated list of keys in the `selectors` or `addresses`
+    /// parameter and returning them along with the results. Without them, batches are looked
+    /// up one key at a time.
+    pub function_signature_batch_query_id: Option<i32>,
+    pub contract_name_batch_query_id: Option<i32>,
+}
+
+impl Default for DuneConfig {
+    fn default() -> DuneConfig {
+        DuneConfig {
+            api_url: DEFAULT_API_URL.to_string(),
+            api_key: None,
+            function_signature_query_id: DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+            contract_name_query_id: DEFAULT_CONTRACT_NAME_QUERY_ID,
+            function_signature_batch_query_id: None,
+            contract_name_batch_query_id: None,
+        }
+    }
+}
 
-    let execution_id = &query_execution_object["execution_id"];
+/// Parses `KEY=value` lines as found in `.env` files.
+fn parse_env_file(content: &str) -> HashMap<String, String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .or_else(|| {
+                    value
+                        .strip_prefix('\'')
+                        .and_then(|value| value.strip_suffix('\''))
+                })
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
 
-    // Wait 3 sec till the query fetching completed
-    thread::sleep(time::Duration::from_secs(5));
+impl DuneConfig {
+    /// Combines the settings with the `DUNE_API_KEY`, `DUNE_API_URL`,
+    /// `DUNE_FUNCTION_SIGNATURE_QUERY_ID`, `DUNE_CONTRACT_NAME_QUERY_ID`,
+    /// `DUNE_FUNCTION_SIGNATURE_BATCH_QUERY_ID` and `DUNE_CONTRACT_NAME_BATCH_QUERY_ID`
+    /// variables from the environment and from the env file. Settings take precedence over the environment,
+    /// which takes precedence over the env file.
+    pub fn load(settings: &DuneSettings) -> DuneConfig {
+        let env_file = read_to_string(&settings.env_file).ok();
+        DuneConfig::resolve(settings, env_file.as_deref(), &|name| {
+            std::env::var(name).ok()
+        })
+    }
 
-    // Get query results
-    let query_results = get_query_results_text(client, execution_id).await?;
+    fn resolve(
+        settings: &DuneSettings,
+        env_file: Option<&str>,
+        env: &dyn Fn(&str) -> Option<String>,
+    ) -> DuneConfig {
+        let env_file = env_file.map(parse_env_file).unwrap_or_default();
+        let variable = |name: &str| {
+            env(name)
+                .or_else(|| env_file.get(name).cloned())
+                .filter(|value| !value.is_empty())
+        };
+        let defaults = DuneConfig::default();
 
-    let query_results_object: Value = serde_json::from_str(query_results.as_str())?;
+        DuneConfig {
+            api_url: settings
+                .api_url
+                .clone()
+                .or_else(|| variable("DUNE_API_URL"))
+                .unwrap_or(defaults.api_url)
+                .trim_end_matches('/')
+                .to_string(),
+            api_key: settings
+                .api_key
+                .clone()
+                .or_else(|| variable("DUNE_API_KEY")),
+            function_signature_query_id: settings
+                .function_signature_query_id
+                .or_else(|| variable("DUNE_FUNCTION_SIGNATURE_QUERY_ID")?.parse().ok())
+                .unwrap_or(defaults.function_signature_query_id),
+            contract_name_query_id: settings
+                .contract_name_query_id
+                .or_else(|| variable("DUNE_CONTRACT_NAME_QUERY_ID")?.parse().ok())
+                .unwrap_or(defaults.contract_name_query_id),
+            function_signature_batch_query_id: settings.function_signature_batch_query_id.or_else(
+                || {
+                    variable("DUNE_FUNCTION_SIGNATURE_BATCH_QUERY_ID")?
+                        .parse()
+                        .ok()
+                },
+            ),
+            contract_name_batch_query_id: settings
+                .contract_name_batch_query_id
+                .or_else(|| variable("DUNE_CONTRACT_NAME_BATCH_QUERY_ID")?.parse().ok()),
+        }
+    }
+}
 
-    Ok(query_results_object["result"]["rows"][0]["signature"].to_string())
+/// Executes the queries resolving selectors and addresses on Dune.
+#[derive(Clone, Debug)]
+pub struct DuneClient {
+    client: Client,
+    config: DuneConfig,
 }
 
-pub async fn get_contract_name(
-    client: &Client,
-    query_id: i32,
-    contract_address: String,
-) -> Result<String> {
-    // TODO: (fix) figure out better way than "replace"
-    let body = r#"{
-        "query_parameters": {
-            "contract_address":"contract_address_string"
+impl DuneClient {
+    pub fn new(client: Client, config: DuneConfig) -> DuneClient {
+        DuneClient { client, config }
+    }
+
+    /// Tells whether an API key is available. Without it no queries can be executed.
+    pub fn is_configured(&self) -> bool {
+        self.config.api_key.is_some()
+    }
+
+    /// Returns all signatures matching the selector. There may be more than one in case of
+    /// a selector collision.
+    pub async fn get_function_name(
+        &self,
+        function_signature: &str,
+    ) -> Result<Vec<String>, DuneError> {
+        let request = ExecuteRequest::new("query_id", function_signature);
+        let rows: Vec<SignatureRow> = self
+            .execute_and_wait(self.config.function_signature_query_id, &request)
+            .await?;
+        Ok(distinct(rows.into_iter().filter_map(|row| row.signature)))
+    }
+
+    /// Returns the names of the contract deployed at the address. Empty if unknown.
+    pub async fn get_contract_names(
+        &self,
+        contract_address: &str,
+    ) -> Result<Vec<String>, DuneError> {
+        let request = ExecuteRequest::new("contract_address", contract_address);
+        let rows: Vec<ContractNameRow> = self
+            .execute_and_wait(self.config.contract_name_query_id, &request)
+            .await?;
+        Ok(distinct(rows.into_iter().filter_map(|row| row.name)))
+    }
+
+    /// Looks up several selectors with a single execution of the batch query, if configured.
+    /// The result contains the selectors looked up successfully, with no signatures for
+    /// unknown ones.
+    pub async fn get_function_names_batch(
+        &self,
+        selectors: &[String],
+    ) -> Result<HashMap<String, Vec<String>>, DuneError> {
+        match self.config.function_signature_batch_query_id {
+            Some(query_id) => {
+                let rows: Vec<BatchSignatureRow> =
+                    self.execute_batch(query_id, "selectors", selectors).await?;
+                Ok(group_rows(
+                    selectors,
+                    rows.into_iter()
+                        .filter_map(|row| Some((row.selector, row.signature?))),
+                ))
+            }
+            None => lookup_each(selectors, |selector| self.get_function_name(selector)).await,
         }
-      }"#
-    .replace("contract_address_string", &contract_address);
+    }
 
-    // Execute query
-    let query_execution = execute_query(client, query_id, body).await?;
+    /// Looks up several addresses, like `get_function_names_batch`.
+    pub async fn get_contract_names_batch(
+        &self,
+        addresses: &[String],
+    ) -> Result<HashMap<String, Vec<String>>, DuneError> {
+        match self.config.contract_name_batch_query_id {
+            Some(query_id) => {
+                let rows: Vec<BatchContractNameRow> =
+                    self.execute_batch(query_id, "addresses", addresses).await?;
+                Ok(group_rows(
+                    addresses,
+                    rows.into_iter()
+                        .filter_map(|row| Some((row.address, row.name?))),
+                ))
+            }
+            None => lookup_each(addresses, |address| self.get_contract_names(address)).await,
+        }
+    }
 
-    let query_execution_object: Value = serde_json::from_str(query_execution.as_str())?;
-    let execution_id = &query_execution_object["execution_id"];
+    async fn execute_batch<Row: DeserializeOwned>(
+        &self,
+        query_id: i32,
+        parameter: &str,
+        keys: &[String],
+    ) -> Result<Vec<Row>, DuneError> {
+        let keys = keys
+            .iter()
+            .map(|key| key.to_lowercase())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.execute_and_wait(query_id, &ExecuteRequest::new(parameter, &keys))
+            .await
+    }
 
-    // Wait 3 sec till the query fetching completed
-    thread::sleep(time::Duration::from_secs(5));
+    /// Executes a query and waits for its results without blocking the thread.
+    async fn execute_and_wait<Row: DeserializeOwned>(
+        &self,
+        query_id: i32,
+        request: &ExecuteRequest<'_>,
+    ) -> Result<Vec<Row>, DuneError> {
+        let execution: ExecuteResponse = self
+            .send(self.client.post(self.query_url(query_id)).json(request))
+            .await?;
+        let execution_id = execution.execution_id;
+        let mut guard = ExecutionGuard {
+            dune_client: self.clone(),
+            execution_id: execution_id.clone(),
+            finished: false,
+        };
 
-    // Get query results
-    let query_results = get_query_results_text(client, execution_id).await?;
+        match self.wait_for_execution(&execution_id).await {
+            Ok(()) => guard.finished = true,
+            // Executions that did not finish in time are cancelled by the guard.
+            Err(error @ DuneError::Pending { .. }) | Err(error @ DuneError::Http(_)) => {
+                return Err(error)
+            }
+            Err(error) => {
+                guard.finished = true;
+                return Err(error);
+            }
+        }
+
+        let results: ResultsResponse<Row> = self
+            .send(
+                self.client
+                    .get(self.execution_url(&execution_id, "results")),
+            )
+            .await?;
+        Ok(results.result.map_or(vec![], |result| result.rows))
+    }
+
+    /// Sends an authenticated request and parses the response body.
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, DuneError> {
+        let api_key = self
+            .config
+            .api_key
+            .as_ref()
+            .ok_or(DuneError::MissingApiKey)?;
+        let response = request.header("x-dune-api-key", api_key).send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            let message = match serde_json::from_str::<ErrorResponse>(&body) {
+                Ok(error) => error.error,
+                Err(_) => body,
+            };
+            return Err(DuneError::Api {
+                status: status.as_u16(),
+                message,
+            });
+        }
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// Polls the execution status with exponential backoff until it reaches a final state.
+    async fn wait_for_execution(&self, execution_id: &str) -> Result<(), DuneError> {
+        let deadline = Instant::now() + EXECUTION_TIMEOUT;
+        let mut interval = INITIAL_POLL_INTERVAL;
+        loop {
+            if Instant::now() + interval > deadline {
+                return Err(DuneError::Pending {
+                    execution_id: execution_id.to_string(),
+                });
+            }
+            tokio::time::sleep(interval).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+
+            let status: StatusResponse = self
+                .send(self.client.get(self.execution_url(execution_id, "status")))
+                .await?;
+            match status.state.as_str() {
+                "QUERY_STATE_COMPLETED" => return Ok(()),
+                "QUERY_STATE_PENDING" | "QUERY_STATE_EXECUTING" => continue,
+                "QUERY_STATE_CANCELLED" => {
+                    return Err(DuneError::Cancelled {
+                        execution_id: execution_id.to_string(),
+                    })
+                }
+                _ => {
+                    return Err(DuneError::Failed {
+                        execution_id: execution_id.to_string(),
+                        state: status.state,
+                    })
+                }
+            }
+        }
+    }
+
+    async fn cancel_execution(&self, execution_id: &str) -> Result<(), DuneError> {
+        let _: serde_json::Value = self
+            .send(self.client.post(self.execution_url(execution_id, "cancel")))
+            .await?;
+        Ok(())
+    }
+
+    fn query_url(&self, query_id: i32) -> String {
+        format!("{}/query/{}/execute", self.config.api_url, query_id)
+    }
 
-    let query_results_object: Value = serde_json::from_str(query_results.as_str())?;
-    Ok(query_results_object["result"]["rows"][0]["name"].to_string())
+    fn execution_url(&self, execution_id: &str, endpoint: &str) -> String {
+        format!(
+            "{}/execution/{}/{}",
+            self.config.api_url, execution_id, endpoint
+        )
+    }
 }
 
-async fn execute_query(client: &Client, query_id: i32, body: String) -> Result<String> {
-    let query_url = format_query_url(query_id);
+fn distinct(values: impl Iterator<Item = String>) -> Vec<String> {
+    let mut distinct_values = vec![];
+    for value in values {
+        if !distinct_values.contains(&value) {
+            distinct_values.push(value);
+        }
+    }
+    distinct_values
+}
 
-    let execution_result = client
-        .post(query_url)
-        .header("x-dune-api-key", DUNE_API_KEY.clone())
-        .body(body)
-        .send()
-        .await?
-        .text()
-        .await?;
+/// Maps every key to its distinct values. Keys without rows map to no values.
+fn group_rows(
+    keys: &[String],
+    rows: impl Iterator<Item = (String, String)>,
+) -> HashMap<String, Vec<String>> {
+    let mut results: HashMap<String, Vec<String>> = keys
+        .iter()
+        .map(|key| (key.to_lowercase(), vec![]))
+        .collect();
+    for (key, value) in rows {
+        if let Some(values) = results.get_mut(&key.to_lowercase()) {
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+    }
+    results
+}
 
-    Ok(execution_result)
+/// Runs a lookup per key, at most `MAX_CONCURRENT_EXECUTIONS` at a time. Fails only if all
+/// lookups fail.
+async fn lookup_each<'a, Lookup, Output>(
+    keys: &'a [String],
+    lookup: Lookup,
+) -> Result<HashMap<String, Vec<String>>, DuneError>
+where
+    Lookup: Fn(&'a str) -> Output,
+    Output: Future<Output = Result<Vec<String>, DuneError>>,
+{
+    let results: Vec<_> = stream::iter(keys.iter().map(|key| lookup(key)))
+        .buffered(MAX_CONCURRENT_EXECUTIONS)
+        .collect()
+        .await;
+    let mut found = HashMap::new();
+    let mut error = None;
+    for (key, result) in keys.iter().zip(results) {
+        match result {
+            Ok(values) => {
+                found.insert(key.to_lowercase(), values);
+            }
+            Err(lookup_error) => error = Some(lookup_error),
+        }
+    }
+    match error {
+        Some(error) if found.is_empty() => Err(error),
+        _ => Ok(found),
+    }
 }
 
-async fn get_query_results_text(client: &Client, execution_id: &Value) -> Result<String> {
-    let execute_url = format_exection_url(execution_id);
+#[derive(Serialize, Debug)]
+struct ExecuteRequest<'a> {
+    query_parameters: HashMap<&'a str, &'a str>,
+}
 
-    let execution_result = client
-        .get(execute_url)
-        .header("x-dune-api-key", DUNE_API_KEY.clone())
-        .send()
-        .await?
-        .text()
-        .await?;
+impl<'a> ExecuteRequest<'a> {
+    fn new(parameter: &'a str, value: &'a str) -> ExecuteRequest<'a> {
+        ExecuteRequest {
+            query_parameters: [(parameter, value)].into_iter().collect(),
+        }
+    }
+}
 
-    Ok(execution_result)
+#[derive(Deserialize, Debug)]
+struct ExecuteResponse {
+    execution_id: String,
 }
 
-// async fn get_query_results_json(client: &Client, execution_id: &Value) -> Result<Value, reqwest::Error> {
-//     let execute_url = format_exection_url(execution_id);
-//     let execution_result = client
-//         .get(execute_url)
-//         .header("x-dune-api-key", DUNE_API_KEY.clone())
-//         .send()
-//         .await?
-//         .json::<serde_json::Value>()
-//         .await?;
-//     Ok(execution_result["result"]["rows"][0]["signature"].clone())
-// }
+#[derive(Deserialize, Debug)]
+struct StatusResponse {
+    state: String,
+}
 
-fn format_query_url(query_id: i32) -> String {
-    let query_url = format!("https://api.dune.com/api/v1/query/{}/execute", query_id);
+#[derive(Deserialize, Debug)]
+struct ResultsResponse<Row> {
+    result: Option<QueryResult<Row>>,
+}
 
-    query_url
+#[derive(Deserialize, Debug)]
+struct QueryResult<Row> {
+    rows: Vec<Row>,
 }
 
-fn format_exection_url(execution_id: &Value) -> String {
-    // TODO: fix the replace
-    format!(
-        "https://api.dune.com/api/v1/execution/{}/results",
-        execution_id
-    )
-    .replace('\"', "")
+#[derive(Deserialize, Debug)]
+struct SignatureRow {
+    signature: Option<String>,
 }
 
-fn get_dune_api_key() -> Result<String> {
-    let env_file = read_to_string(".env")
-        .unwrap()
-        .replace("DUNE_API_KEY=", "")
-        .replace('\"', "");
+#[derive(Deserialize, Debug)]
+struct ContractNameRow {
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchSignatureRow {
+    selector: String,
+    signature: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct BatchContractNameRow {
+    address: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: String,
+}
 
-    Ok(env_file)
+/// Cancels the execution on Dune when dropped before being marked as finished. Dropping
+/// happens e.g. when the editor cancels the request waiting for the results.
+struct ExecutionGuard {
+    dune_client: DuneClient,
+    execution_id: String,
+    finished: bool,
+}
+
+impl Drop for ExecutionGuard {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let dune_client = self.dune_client.clone();
+            let execution_id = self.execution_id.clone();
+            runtime.spawn(async move {
+                // Nothing to do if this fails, the execution times out on Dune eventually.
+                let _ = dune_client.cancel_execution(&execution_id).await;
+            });
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dune_mock::{MockDune, MockExecution, MockOutcome};
+    use serde_json::json;
+    use std::path::PathBuf;
+
+    #[test]
+    fn request_body() {
+        let request = ExecuteRequest::new("contract_address", "0x12\"}, \"injected\": \"1");
+
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({"query_parameters": {"contract_address": "0x12\"}, \"injected\": \"1"}})
+        );
+    }
+
+    #[test]
+    fn result_rows() {
+        let results: ResultsResponse<ContractNameRow> = serde_json::from_value(json!({
+            "execution_id": "01ABC",
+            "state": "QUERY_STATE_COMPLETED",
+            "result": {
+                "rows": [{"name": "SwapRouter"}, {"name": null}, {"name": "SwapRouter"}],
+                "metadata": {"column_names": ["name"]}
+            }
+        }))
+        .unwrap();
+        let rows = results.result.unwrap().rows;
+        assert_eq!(
+            distinct(rows.into_iter().filter_map(|row| row.name)),
+            vec!["SwapRouter"]
+        );
+
+        let results: ResultsResponse<SignatureRow> = serde_json::from_value(json!({
+            "execution_id": "01ABC",
+            "state": "QUERY_STATE_COMPLETED",
+            "result": {"rows": [{"signature": null}, {"signature": "balanceOf(address)"}]}
+        }))
+        .unwrap();
+        let rows = results.result.unwrap().rows;
+        assert_eq!(
+            distinct(rows.into_iter().filter_map(|row| row.signature)),
+            vec!["balanceOf(address)"]
+        );
+
+        let rows = vec![
+            (
+                "0xA9059CBB".to_string(),
+                "transfer(address,uint256)".to_string(),
+            ),
+            (
+                "0xa9059cbb".to_string(),
+                "transfer(address,uint256)".to_string(),
+            ),
+        ];
+        let selectors = ["0xa9059cbb".to_string(), "0x12345678".to_string()];
+        let results = group_rows(&selectors, rows.into_iter());
+        assert_eq!(results["0xa9059cbb"], vec!["transfer(address,uint256)"]);
+        assert!(results["0x12345678"].is_empty());
+    }
+
+    #[test]
+    fn config_sources() {
+        let env_file = "# Dune\nexport DUNE_API_KEY=\"file key\"\nDUNE_CONTRACT_NAME_QUERY_ID=7\n";
+        let env = |name: &str| match name {
+            "DUNE_API_URL" => Some("http://127.0.0.1:8080/api/v1/".to_string()),
+            "DUNE_CONTRACT_NAME_QUERY_ID" => Some("8".to_string()),
+            _ => None,
+        };
+        let mut settings = DuneSettings {
+            env_file: PathBuf::from(".env"),
+            ..DuneSettings::default()
+        };
+
+        assert_eq!(
+            DuneConfig::resolve(&settings, Some(env_file), &env),
+            DuneConfig {
+                api_url: "http://127.0.0.1:8080/api/v1".to_string(),
+                api_key: Some("file key".to_string()),
+                function_signature_query_id: DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+                contract_name_query_id: 8,
+                function_signature_batch_query_id: None,
+                contract_name_batch_query_id: None,
+            }
+        );
+
+        settings.api_key = Some("settings key".to_string());
+        settings.contract_name_query_id = Some(9);
+        let config = DuneConfig::resolve(&settings, None, &|_| None);
+        assert_eq!(config.api_key, Some("settings key".to_string()));
+        assert_eq!(config.api_url, DEFAULT_API_URL);
+        assert_eq!(config.contract_name_query_id, 9);
+    }
+
+    async fn mock_client() -> (MockDune, DuneClient) {
+        let mock_dune = MockDune::start().await;
+        let dune_client = DuneClient::new(reqwest::Client::new(), mock_dune.config());
+        (mock_dune, dune_client)
+    }
+
+    #[tokio::test]
+    async fn get_function_name() {
+        let (mock_dune, dune_client) = mock_client().await;
+        mock_dune.respond(
+            DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+            "0x70a08231",
+            MockExecution {
+                pending_polls: 2,
+                outcome: MockOutcome::Rows(vec![
+                    json!({"signature": "balanceOf(address)"}),
+                    json!({"signature": "balanceOf(address)"}),
+                ]),
+            },
+        );
+
+        assert_eq!(
+            dune_client.get_function_name("0x70a08231").await.unwrap(),
+            vec!["balanceOf(address)"]
+        );
+        // Unknown selectors have no rows.
+        assert!(dune_client
+            .get_function_name("0x12345678")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(mock_dune.cancelled().is_empty());
+    }
+
+    #[tokio::test]
+    async fn get_contract_names() {
+        let (mock_dune, dune_client) = mock_client().await;
+        let address = "0xe592427a0aece92de3edee1f18e0157c05861564";
+        mock_dune.respond(
+            DEFAULT_CONTRACT_NAME_QUERY_ID,
+            address,
+            MockExecution::rows(vec![json!({"name": "SwapRouter"}), json!({"name": null})]),
+        );
+
+        assert_eq!(
+            dune_client.get_contract_names(address).await.unwrap(),
+            vec!["SwapRouter"]
+        );
+        assert_eq!(
+            mock_dune.executed(),
+            vec![(DEFAULT_CONTRACT_NAME_QUERY_ID, address.to_string())]
+        );
+
+        let names = dune_client
+            .get_contract_names_batch(&[address.to_string(), "0x00".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(names[address], vec!["SwapRouter"]);
+        assert!(names["0x00"].is_empty());
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit() {
+        let (mock_dune, dune_client) = mock_client().await;
+        let selectors: Vec<String> = (0..10).map(|index| format!("0x{:08x}", index)).collect();
+        for selector in &selectors {
+            mock_dune.respond(
+                DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+                selector,
+                MockExecution {
+                    pending_polls: 1,
+                    outcome: MockOutcome::Rows(vec![]),
+                },
+            );
+        }
+
+        let signatures = dune_client
+            .get_function_names_batch(&selectors)
+            .await
+            .unwrap();
+        assert_eq!(signatures.len(), selectors.len());
+        assert_eq!(mock_dune.executed().len(), selectors.len());
+        assert!(mock_dune.max_running() <= MAX_CONCURRENT_EXECUTIONS);
+    }
+
+    #[tokio::test]
+    async fn execution_errors() {
+        let (mock_dune, dune_client) = mock_client().await;
+        mock_dune.respond(
+            DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+            "0x70a08231",
+            MockExecution {
+                pending_polls: 1,
+                outcome: MockOutcome::State("QUERY_STATE_FAILED"),
+            },
+        );
+        assert!(matches!(
+            dune_client.get_function_name("0x70a08231").await,
+            Err(DuneError::Failed { state, .. }) if state == "QUERY_STATE_FAILED"
+        ));
+
+        let unauthorized = DuneClient::new(
+            reqwest::Client::new(),
+            DuneConfig {
+                api_key: Some("wrong key".to_string()),
+                ..mock_dune.config()
+            },
+        );
+        assert!(matches!(
+            unauthorized.get_function_name("0x70a08231").await,
+            Err(DuneError::Api { status: 401, message }) if message == "invalid API Key"
+        ));
+
+        let unconfigured = DuneClient::new(
+            reqwest::Client::new(),
+            DuneConfig {
+                api_key: None,
+                ..mock_dune.config()
+            },
+        );
+        assert!(matches!(
+            unconfigured.get_function_name("0x70a08231").await,
+            Err(DuneError::MissingApiKey)
+        ));
+    }
+
+    #[tokio::test]
+    async fn cancel_on_drop() {
+        let (mock_dune, dune_client) = mock_client().await;
+        mock_dune.respond(
+            DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+            "0x70a08231",
+            MockExecution {
+                pending_polls: usize::MAX,
+                outcome: MockOutcome::Rows(vec![]),
+            },
+        );
 
-    // FIXME: These tests won't pass in CI without the API key.
-    // We need a key to a sandbox or tests that mock the API.
-    //#[tokio::test]
-    //async fn test_get_function_name() -> Result<()> {
-    //    let client = reqwest::Client::new();
-    //    let function_signature = "0x70a08231".to_owned();
-    //    println!("Function signature: {}", function_signature);
-    //    let function_name =
-    //        get_function_name(&client, QUERY_FUNCTION_NAME_SIGNATURE, function_signature).await?;
-    //    println!("Function name:      {}", function_name);
-    //
-    //    assert_eq!(function_name, "balanceOf(address)");
-    //    Ok(())
-    //}
-    //
-    //#[tokio::test]
-    //async fn test_get_contract_name() -> Result<()> {
-    //    let client = reqwest::Client::new();
-    //    let contract_address = "0xe592427a0aece92de3edee1f18e0157c05861564".to_owned();
-    //    println!("Contract Address:{}", contract_address);
-    //    let contract_name =
-    //        get_contract_name(&client, QUERY_CONTRACT_NAME, contract_address).await?;
-    //    println!("Contract Name:   {}", contract_name);
-    //
-    //    assert_eq!(contract_name, "SwapRouter");
-    //
-    //    Ok(())
-    //}
+        // Gives up before the first status check.
+        let lookup = dune_client.get_function_name("0x70a08231");
+        assert!(tokio::time::timeout(Duration::from_millis(100), lookup)
+            .await
+            .is_err());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(mock_dune.cancelled(), vec!["01MOCK0"]);
+    }
 }
@@ -0,0 +1,271 @@
+use crate::dune_apis::DuneConfig;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+pub const MOCK_API_KEY: &str = "mock-api-key";
+
+/// How an execution of a query behaves.
+#[derive(Clone, Debug)]
+pub struct MockExecution {
+    /// Number of status checks answered with a pending state before the final one.
+    pub pending_polls: usize,
+    pub outcome: MockOutcome,
+}
+
+#[derive(Clone, Debug)]
+pub enum MockOutcome {
+    Rows(Vec<Value>),
+    /// Ends in the given state, e.g. `QUERY_STATE_FAILED`.
+    State(&'static str),
+}
+
+impl MockExecution {
+    pub fn rows(rows: Vec<Value>) -> MockExecution {
+        MockExecution {
+            pending_polls: 0,
+            outcome: MockOutcome::Rows(rows),
+        }
+    }
+}
+
+#[derive(Default, Debug)]
+struct MockState {
+    /// Keyed by query id and parameter value. Other queries return no rows.
+    queries: HashMap<(i32, String), MockExecution>,
+    executions: HashMap<String, MockExecution>,
+    executed: Vec<(i32, String)>,
+    cancelled: Vec<String>,
+    /// Executions that did not complete, fail or get cancelled yet.
+    running: usize,
+    max_running: usize,
+}
+
+/// In-process stand-in for the Dune API, used by tests instead of the real service.
+///
+/// Emulates the execute, status, results and cancel endpoints closely enough for
+/// `DuneClient`: executions can stay pending for a number of status checks, end in a failed
+/// state or return rows, and requests with a wrong API key are rejected.
+#[derive(Clone, Debug)]
+pub struct MockDune {
+    api_url: String,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockDune {
+    /// Starts serving on a free local port until the runtime shuts down.
+    pub async fn start() -> MockDune {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mock_dune = MockDune {
+            api_url: format!("http://{}/api/v1", listener.local_addr().unwrap()),
+            state: Arc::new(Mutex::new(MockState::default())),
+        };
+        let state = mock_dune.state.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, state.clone()));
+            }
+        });
+        mock_dune
+    }
+
+    /// Configuration of a client talking to the mock.
+    pub fn config(&self) -> DuneConfig {
+        DuneConfig {
+            api_url: self.api_url.clone(),
+            api_key: Some(MOCK_API_KEY.to_string()),
+            ..DuneConfig::default()
+        }
+    }
+
+    /// Sets how executions of the query with the given parameter value behave.
+    pub fn respond(&self, query_id: i32, parameter: &str, execution: MockExecution) {
+        self.state
+            .lock()
+            .unwrap()
+            .queries
+            .insert((query_id, parameter.to_string()), execution);
+    }
+
+    /// Query ids and parameter values of all executions so far.
+    pub fn executed(&self) -> Vec<(i32, String)> {
+        self.state.lock().unwrap().executed.clone()
+    }
+
+    pub fn cancelled(&self) -> Vec<String> {
+        self.state.lock().unwrap().cancelled.clone()
+    }
+
+    /// Largest number of executions running at the same time so far.
+    pub fn max_running(&self) -> usize {
+        self.state.lock().unwrap().max_running
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    api_key: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Serves a single request, then closes the connection.
+async fn handle_connection(mut stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let request = match read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+    let (status, body) = route(&request, &state);
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    let mut buffer = vec![];
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        let mut chunk = [0; 1024];
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let mut api_key = None;
+    let mut content_length = 0;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_lowercase().as_str() {
+                "x-dune-api-key" => api_key = Some(value.trim().to_string()),
+                "content-length" => content_length = value.trim().parse().ok()?,
+                _ => {}
+            }
+        }
+    }
+
+    let mut body = buffer[header_end..].to_vec();
+    while body.len() < content_length {
+        let mut chunk = [0; 1024];
+        let read = stream.read(&mut chunk).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    Some(Request {
+        method,
+        path,
+        api_key,
+        body,
+    })
+}
+
+fn route(request: &Request, state: &Mutex<MockState>) -> (&'static str, Value) {
+    if request.api_key.as_deref() != Some(MOCK_API_KEY) {
+        return ("401 Unauthorized", json!({"error": "invalid API Key"}));
+    }
+    let mut state = state.lock().unwrap();
+    let state = &mut *state;
+    let segments: Vec<&str> = request
+        .path
+        .trim_start_matches("/api/v1/")
+        .split('/')
+        .collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["query", query_id, "execute"]) => {
+            let query_id: i32 = match query_id.parse() {
+                Ok(query_id) => query_id,
+                Err(_) => return ("400 Bad Request", json!({"error": "invalid query id"})),
+            };
+            let body: Value = serde_json::from_slice(&request.body).unwrap_or(Value::Null);
+            let parameter = body["query_parameters"]
+                .as_object()
+                .and_then(|parameters| parameters.values().next())
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            let execution = state
+                .queries
+                .get(&(query_id, parameter.clone()))
+                .cloned()
+                .unwrap_or_else(|| MockExecution::rows(vec![]));
+            let execution_id = format!("01MOCK{}", state.executions.len());
+            state.executions.insert(execution_id.clone(), execution);
+            state.executed.push((query_id, parameter));
+            state.running += 1;
+            state.max_running = state.max_running.max(state.running);
+            (
+                "200 OK",
+                json!({"execution_id": execution_id, "state": "QUERY_STATE_PENDING"}),
+            )
+        }
+        ("GET", ["execution", execution_id, "status"]) => {
+            let execution = match state.executions.get_mut(*execution_id) {
+                Some(execution) => execution,
+                None => return ("404 Not Found", json!({"error": "execution not found"})),
+            };
+            let execution_state = if execution.pending_polls > 0 {
+                execution.pending_polls -= 1;
+                "QUERY_STATE_EXECUTING"
+            } else {
+                match execution.outcome {
+                    MockOutcome::Rows(_) => "QUERY_STATE_COMPLETED",
+                    MockOutcome::State(execution_state) => {
+                        state.running = state.running.saturating_sub(1);
+                        execution_state
+                    }
+                }
+            };
+            (
+                "200 OK",
+                json!({"execution_id": execution_id, "state": execution_state}),
+            )
+        }
+        ("GET", ["execution", execution_id, "results"]) => {
+            match state.executions.get(*execution_id) {
+                Some(MockExecution {
+                    pending_polls: 0,
+                    outcome: MockOutcome::Rows(rows),
+                }) => {
+                    state.running = state.running.saturating_sub(1);
+                    (
+                        "200 OK",
+                        json!({
+                            "execution_id": execution_id,
+                            "state": "QUERY_STATE_COMPLETED",
+                            "result": {"rows": rows},
+                        }),
+                    )
+                }
+                Some(_) => (
+                    "400 Bad Request",
+                    json!({"error": "execution has no results"}),
+                ),
+                None => ("404 Not Found", json!({"error": "execution not found"})),
+            }
+        }
+        ("POST", ["execution", execution_id, "cancel"]) => {
+            state.cancelled.push(execution_id.to_string());
+            state.running = state.running.saturating_sub(1);
+            ("200 OK", json!({"success": true}))
+        }
+        _ => ("404 Not Found", json!({"error": "not found"})),
+    }
+}
@@ -0,0 +1,231 @@
+use crate::abi::format_bytes;
+use crate::abi_artifacts::EventSignature;
+use crate::diagnostics::Finding;
+use crate::literal_value::evaluate_literal;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{Block, Expression, FunctionCall, Literal};
+
+/// Returns the events known for a topic in `0x` + 64 hex digits form.
+pub type EventLookup<'a> = &'a dyn Fn(&str) -> Vec<EventSignature>;
+
+/// A literal passed as the first topic of `log1` to `log4`, which holds the event topic
+/// unless the event is anonymous.
+#[derive(Clone, Debug)]
+pub struct EventTopic {
+    pub literal: Literal,
+    /// The topic as a full 32-byte word.
+    pub topic: String,
+    /// Name of the log instruction, e.g. `log3`.
+    pub instruction: String,
+    /// Number of topics following the event topic.
+    pub indexed_topics: usize,
+}
+
+/// Returns the event topic passed to the call, if the call is to `log1` to `log4` and the
+/// first topic is a 32-byte hex literal. Shorter literals are rarely event topics.
+fn event_topic(function_call: &FunctionCall) -> Option<EventTopic> {
+    let topic_count = match function_call.function.name.as_str() {
+        "log1" => 1,
+        "log2" => 2,
+        "log3" => 3,
+        "log4" => 4,
+        _ => return None,
+    };
+    // The memory offset and size come before the topics.
+    let literal = match function_call.arguments.get(2)? {
+        Expression::Literal(literal)
+            if literal.literal.starts_with("0x") && literal.literal.len() == 66 =>
+        {
+            literal
+        }
+        _ => return None,
+    };
+    let value = evaluate_literal(&literal.literal).ok()?;
+    Some(EventTopic {
+        literal: literal.clone(),
+        topic: format_bytes(&value.0),
+        instruction: function_call.function.name.clone(),
+        indexed_topics: topic_count - 1,
+    })
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+struct EventTopicFinder {
+    pub cursor_location: usize,
+    pub found_topic: Option<EventTopic>,
+}
+
+impl ASTVisitor for EventTopicFinder {
+    fn visit_function_call(&mut self, function_call: &FunctionCall) {
+        if let Some(event_topic) = event_topic(function_call) {
+            if let Some(location) = &event_topic.literal.location {
+                if location.start <= self.cursor_location && self.cursor_location < location.end {
+                    self.found_topic = Some(event_topic);
+                }
+            }
+        }
+        for argument in &function_call.arguments {
+            self.visit_expression(argument);
+        }
+    }
+}
+
+/// Returns the event topic literal at the cursor.
+pub fn find_event_topic(ast: &Block, cursor_position: usize) -> Option<EventTopic> {
+    let mut event_topic_finder = EventTopicFinder {
+        cursor_location: cursor_position,
+        found_topic: None,
+    };
+    event_topic_finder.visit_block(ast);
+    event_topic_finder.found_topic
+}
+
+struct EventChecker<'a> {
+    pub known_events: EventLookup<'a>,
+    pub findings: Vec<Finding>,
+}
+
+impl<'a> ASTVisitor for EventChecker<'a> {
+    fn visit_function_call(&mut self, function_call: &FunctionCall) {
+        if let Some(event_topic) = event_topic(function_call) {
+            self.check_topic_count(&event_topic);
+        }
+        for argument in &function_call.arguments {
+            self.visit_expression(argument);
+        }
+    }
+}
+
+impl<'a> EventChecker<'a> {
+    fn check_topic_count(&mut self, event_topic: &EventTopic) {
+        let location = match &event_topic.literal.location {
+            Some(location) => location,
+            None => return,
+        };
+        // Anonymous events have no event topic, so the literal cannot identify them.
+        let events: Vec<EventSignature> = (self.known_events)(&event_topic.topic)
+            .into_iter()
+            .filter(|event| !event.anonymous)
+            .collect();
+        if events.is_empty()
+            || events
+                .iter()
+                .any(|event| event.indexed_count == event_topic.indexed_topics)
+        {
+            return;
+        }
+
+        let mut signatures: Vec<&str> = vec![];
+        let mut indexed_counts: Vec<usize> = vec![];
+        for event in &events {
+            if !signatures.contains(&event.signature.as_str()) {
+                signatures.push(&event.signature);
+            }
+            if !indexed_counts.contains(&event.indexed_count) {
+                indexed_counts.push(event.indexed_count);
+            }
+        }
+        indexed_counts.sort_unstable();
+        let expected = indexed_counts
+            .iter()
+            .map(|count| count.to_string())
+            .collect::<Vec<_>>()
+            .join(" or ");
+        self.findings.push(Finding::warning(
+            location.clone(),
+            format!(
+                "{} passes {}, but {} {} {} {}.",
+                event_topic.instruction,
+                plural(event_topic.indexed_topics, "indexed topic"),
+                if signatures.len() == 1 {
+                    "event"
+                } else {
+                    "events"
+                },
+                signatures.join(", "),
+                if signatures.len() == 1 { "has" } else { "have" },
+                if indexed_counts == [1] {
+                    "1 indexed parameter".to_string()
+                } else {
+                    format!("{} indexed parameters", expected)
+                }
+            ),
+        ));
+    }
+}
+
+/// Checks that `log1` to `log4` pass as many topics as the event identified by the event
+/// topic has indexed parameters.
+pub fn check_events(ast: &Block, known_events: Option<EventLookup>) -> Vec<Finding> {
+    let known_events = match known_events {
+        Some(known_events) => known_events,
+        None => return vec![],
+    };
+    let mut event_checker = EventChecker {
+        known_events,
+        findings: vec![],
+    };
+    event_checker.visit_block(ast);
+    event_checker.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::signature_db::SignatureDatabase;
+    use indoc::indoc;
+    use yultsur::yul_parser::parse_block;
+
+    #[test]
+    fn topic_counts() {
+        let source_code = indoc! {"
+            {
+                let from := caller()
+                log3(0, 0x20, 0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef, from, 0)
+                log2(0, 0x20, 0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef, from)
+                log1(0, 0x20, 0xbc7cd75a20ee27fd9adebab32041f755214dbc6bffa90cc0225b39da2e5c2d3b)
+                log1(0, 0x20, 0x1234)
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let database = SignatureDatabase::bundled();
+        let lookup = |topic: &str| database.lookup_events(topic);
+        let findings = check_events(&ast, Some(&lookup as EventLookup));
+        let messages: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "log2 passes 1 indexed topic, but event Transfer(address,address,uint256) has 2 or 3 indexed parameters.",
+                "log1 passes 0 indexed topics, but event Upgraded(address) has 1 indexed parameter.",
+            ]
+        );
+    }
+
+    #[test]
+    fn topic_at_cursor() {
+        let source_code = "{ log2(0, 0x20, 0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925, 1) log1(0, 0x20, 0x1234) log1(0, 0x20, 1) }";
+        let ast = parse_block(source_code).unwrap();
+
+        let event_topic = find_event_topic(&ast, source_code.find("0x8c5b").unwrap() + 4).unwrap();
+        assert_eq!(event_topic.instruction, "log2");
+        assert_eq!(event_topic.indexed_topics, 1);
+        assert_eq!(
+            event_topic.topic,
+            "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925"
+        );
+        assert!(find_event_topic(&ast, source_code.find("0x20").unwrap()).is_none());
+        assert!(find_event_topic(&ast, source_code.find("0x1234").unwrap()).is_none());
+        assert!(find_event_topic(&ast, source_code.rfind('1').unwrap()).is_none());
+    }
+}
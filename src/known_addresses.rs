@@ -0,0 +1,269 @@
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KnownAddressKind {
+    Precompile,
+    /// A contract deployed at the same address on many chains, or by the protocol itself.
+    Singleton,
+}
+
+/// An address whose code is known without looking it up, like a precompile.
+#[derive(Debug)]
+pub struct KnownAddress {
+    /// 20 bytes in lowercase, as returned by `normalized_value`.
+    pub address: &'static str,
+    pub name: &'static str,
+    pub kind: KnownAddressKind,
+    pub note: Option<&'static str>,
+    pub input: Option<&'static str>,
+    pub output: Option<&'static str>,
+    pub gas: Option<&'static str>,
+}
+
+const fn precompile(
+    address: &'static str,
+    name: &'static str,
+    input: &'static str,
+    output: &'static str,
+    gas: &'static str,
+) -> KnownAddress {
+    KnownAddress {
+        address,
+        name,
+        kind: KnownAddressKind::Precompile,
+        note: None,
+        input: Some(input),
+        output: Some(output),
+        gas: Some(gas),
+    }
+}
+
+const KNOWN_ADDRESSES: &[KnownAddress] = &[
+    precompile(
+        "0x0000000000000000000000000000000000000001",
+        "ecrecover",
+        "128 bytes: hash, v, r, s",
+        "32 bytes: the signer address, left-padded. Empty if recovery fails",
+        "3000",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000002",
+        "sha256",
+        "Any bytes",
+        "32 bytes: the SHA-256 hash",
+        "60 + 12 per 32-byte word of input",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000003",
+        "ripemd160",
+        "Any bytes",
+        "32 bytes: the RIPEMD-160 hash, left-padded",
+        "600 + 120 per 32-byte word of input",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000004",
+        "identity",
+        "Any bytes",
+        "The input",
+        "15 + 3 per 32-byte word of input",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000005",
+        "modexp",
+        "Lengths of B, E and M (32 bytes each), then B, E and M",
+        "B^E mod M, as many bytes as M",
+        "max(200, multiplication complexity × iteration count / 3) (EIP-2565)",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000006",
+        "ecAdd",
+        "128 bytes: two alt_bn128 points (x1, y1, x2, y2)",
+        "64 bytes: the sum (x, y)",
+        "150",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000007",
+        "ecMul",
+        "96 bytes: an alt_bn128 point (x, y) and a scalar s",
+        "64 bytes: the product (x, y)",
+        "6000",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000008",
+        "ecPairing",
+        "k × 192 bytes: a G1 point (x, y) and a G2 point (x_im, x_re, y_im, y_re) per pair",
+        "32 bytes: 1 if the pairing check succeeds, 0 otherwise",
+        "45000 + 34000 per pair",
+    ),
+    precompile(
+        "0x0000000000000000000000000000000000000009",
+        "blake2f",
+        "213 bytes: rounds (4 bytes), h (64 bytes), m (128 bytes), t (16 bytes), final block flag (1 byte)",
+        "64 bytes: the new state h",
+        "1 per round",
+    ),
+    precompile(
+        "0x000000000000000000000000000000000000000a",
+        "point evaluation",
+        "192 bytes: versioned hash, z, y (32 bytes each), commitment, proof (48 bytes each)",
+        "64 bytes: FIELD_ELEMENTS_PER_BLOB and BLS_MODULUS",
+        "50000",
+    ),
+    KnownAddress {
+        note: Some(
+            "Available on rollups implementing RIP-7212 and on Ethereum since EIP-7951.",
+        ),
+        ..precompile(
+            "0x0000000000000000000000000000000000000100",
+            "p256verify",
+            "160 bytes: hash, r, s, public key x, public key y",
+            "32 bytes: 1 if the signature is valid. Empty otherwise",
+            "3450 (RIP-7212), 6900 (EIP-7951)",
+        )
+    },
+    KnownAddress {
+        address: "0x4e59b44847b379578588920ca78fbf26c0b4956c",
+        name: "CREATE2 deployer",
+        kind: KnownAddressKind::Singleton,
+        note: Some("Deterministic deployment proxy, deployed at the same address on most chains."),
+        input: Some("Salt (32 bytes), then the init code"),
+        output: Some("20 bytes: the address of the deployed contract, not padded"),
+        gas: None,
+    },
+    KnownAddress {
+        address: "0x000f3df6d732807ef1319fb7b8bb8522d0beac02",
+        name: "beacon roots",
+        kind: KnownAddressKind::Singleton,
+        note: Some("System contract storing parent beacon block roots (EIP-4788)."),
+        input: Some("32 bytes: a timestamp"),
+        output: Some(
+            "32 bytes: the parent beacon block root of the block with that timestamp. Reverts if the timestamp is unknown",
+        ),
+        gas: None,
+    },
+    KnownAddress {
+        address: "0x0000f90827f1c53a10cb7a02335b175320002935",
+        name: "history storage",
+        kind: KnownAddressKind::Singleton,
+        note: Some("System contract storing recent block hashes (EIP-2935)."),
+        input: Some("32 bytes: a block number"),
+        output: Some("32 bytes: the block hash. Reverts outside the last 8191 blocks"),
+        gas: None,
+    },
+    KnownAddress {
+        address: "0xca11bde05977b3631167028862be2a173976ca11",
+        name: "Multicall3",
+        kind: KnownAddressKind::Singleton,
+        note: None,
+        input: None,
+        output: None,
+        gas: None,
+    },
+    KnownAddress {
+        address: "0x000000000022d473030f116ddee9f6b43ac78ba3",
+        name: "Permit2",
+        kind: KnownAddressKind::Singleton,
+        note: None,
+        input: None,
+        output: None,
+        gas: None,
+    },
+    KnownAddress {
+        address: "0x5ff137d4b0fdcd49dca30c7cf57e578a026d2789",
+        name: "EntryPoint v0.6",
+        kind: KnownAddressKind::Singleton,
+        note: Some("ERC-4337 entry point."),
+        input: None,
+        output: None,
+        gas: None,
+    },
+    KnownAddress {
+        address: "0x0000000071727de22e5e9d8baf0edac6f37da032",
+        name: "EntryPoint v0.7",
+        kind: KnownAddressKind::Singleton,
+        note: Some("ERC-4337 entry point."),
+        input: None,
+        output: None,
+        gas: None,
+    },
+];
+
+/// Returns the precompile or singleton at the address, given as 20 bytes in lowercase.
+pub fn known_address(address: &str) -> Option<&'static KnownAddress> {
+    KNOWN_ADDRESSES
+        .iter()
+        .find(|known_address| known_address.address == address)
+}
+
+impl KnownAddress {
+    /// Describes the contract in Markdown, with its input and output layout and gas cost.
+    pub fn describe(&self) -> String {
+        let title = match self.kind {
+            KnownAddressKind::Precompile => {
+                let digits = self.address[2..].trim_start_matches('0');
+                format!(
+                    "Precompile `{}` at `0x{:0>width$}`",
+                    self.name,
+                    digits,
+                    width = digits.len() + digits.len() % 2
+                )
+            }
+            KnownAddressKind::Singleton => format!("`{}`", self.name),
+        };
+        let mut lines = vec![];
+        if let Some(input) = self.input {
+            lines.push(format!("- Input: {}", input));
+        }
+        if let Some(output) = self.output {
+            lines.push(format!("- Output: {}", output));
+        }
+        if let Some(gas) = self.gas {
+            lines.push(format!("- Gas: {}", gas));
+        }
+
+        let mut description = title;
+        if let Some(note) = self.note {
+            description.push_str(&format!("\n\n{}", note));
+        }
+        if !lines.is_empty() {
+            description.push_str(&format!("\n\n{}", lines.join("\n")));
+        }
+        description
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::is_address;
+
+    #[test]
+    fn addresses_are_normalized() {
+        for known_address in KNOWN_ADDRESSES {
+            assert!(is_address(known_address.address));
+            assert_eq!(known_address.address, known_address.address.to_lowercase());
+        }
+    }
+
+    #[test]
+    fn descriptions() {
+        assert_eq!(
+            known_address("0x0000000000000000000000000000000000000001")
+                .unwrap()
+                .describe(),
+            "Precompile `ecrecover` at `0x01`\n\n\
+             - Input: 128 bytes: hash, v, r, s\n\
+             - Output: 32 bytes: the signer address, left-padded. Empty if recovery fails\n\
+             - Gas: 3000"
+        );
+        assert!(known_address("0x0000000000000000000000000000000000000100")
+            .unwrap()
+            .describe()
+            .starts_with("Precompile `p256verify` at `0x0100`\n\nAvailable on rollups"));
+        assert_eq!(
+            known_address("0xca11bde05977b3631167028862be2a173976ca11")
+                .unwrap()
+                .describe(),
+            "`Multicall3`"
+        );
+        assert!(known_address("0x000000000000000000000000000000000000000b").is_none());
+    }
+}
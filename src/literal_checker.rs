@@ -0,0 +1,152 @@
+use crate::address::{checksum_status, ChecksumStatus};
+use crate::diagnostics::{Edit, Finding, Fix};
+use crate::dispatcher::is_dispatcher;
+use crate::literal_value::{evaluate_literal, U256};
+use std::collections::HashMap;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{Block, Literal, SourceLocation, Switch};
+
+/// Validates the values of literals and the EIP-55 checksums of addresses, and reports `case`
+/// labels repeating an earlier value in the same `switch`.
+struct LiteralChecker {
+    pub findings: Vec<Finding>,
+}
+
+impl ASTVisitor for LiteralChecker {
+    fn visit_literal(&mut self, literal: &Literal) {
+        if let (Err(error), Some(location)) =
+            (evaluate_literal(&literal.literal), &literal.location)
+        {
+            self.findings
+                .push(Finding::error(location.clone(), error.to_string()));
+        }
+        if let (Some(ChecksumStatus::Invalid { expected }), Some(location)) =
+            (checksum_status(&literal.literal), &literal.location)
+        {
+            self.findings.push(
+                Finding::warning(
+                    location.clone(),
+                    format!("Invalid EIP-55 address checksum. Expected {}.", expected),
+                )
+                .with_fix(Fix {
+                    title: format!("Change to {}", expected),
+                    edits: vec![Edit {
+                        location: location.clone(),
+                        replacement: expected,
+                    }],
+                }),
+            );
+        }
+    }
+
+    fn visit_switch(&mut self, switch: &Switch) {
+        // Duplicate selectors in dispatchers get a more specific error from the dispatcher checker.
+        if !is_dispatcher(switch) {
+            let mut seen_values: HashMap<U256, SourceLocation> = HashMap::new();
+            for literal in switch.cases.iter().filter_map(|case| case.literal.as_ref()) {
+                if let (Ok(value), Some(location)) =
+                    (evaluate_literal(&literal.literal), &literal.location)
+                {
+                    match seen_values.get(&value) {
+                        Some(previous_location) => self.findings.push(
+                            Finding::error(
+                                location.clone(),
+                                format!("Duplicate case value {}.", value.to_hex_string()),
+                            )
+                            .with_related(
+                                previous_location.clone(),
+                                "The value is first used here.".to_string(),
+                            ),
+                        ),
+                        None => {
+                            seen_values.insert(value, location.clone());
+                        }
+                    }
+                }
+            }
+        }
+
+        self.visit_expression(&switch.expression);
+        for case in &switch.cases {
+            self.visit_case(case);
+        }
+    }
+}
+
+pub fn check_literals(ast: &Block) -> Vec<Finding> {
+    let mut literal_checker = LiteralChecker { findings: vec![] };
+    literal_checker.visit_block(ast);
+    literal_checker.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use yultsur::yul_parser::parse_block;
+
+    #[test]
+    fn invalid_literals_and_duplicate_cases() {
+        let source_code = indoc! {"
+            {
+                let x := 0x10000000000000000000000000000000000000000000000000000000000000000
+                switch x
+                case 1 {}
+                case 0x01 {}
+                case 2 {}
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_literals(&ast);
+        let messages: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Number literal exceeds the maximum value of 2^256-1.",
+                "Duplicate case value 0x1.",
+            ]
+        );
+        assert_eq!(
+            &source_code[findings[1].location.start..findings[1].location.end],
+            "0x01"
+        );
+        assert_eq!(
+            &source_code
+                [findings[1].related[0].location.start..findings[1].related[0].location.end],
+            "1"
+        );
+    }
+
+    #[test]
+    fn address_checksums() {
+        let source_code = indoc! {"
+            {
+                pop(balance(0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed))
+                pop(balance(0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed))
+                pop(balance(0xe592427a0aece92de3edee1f18e0157c05861564))
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_literals(&ast);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            "Invalid EIP-55 address checksum. Expected 0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed."
+        );
+        let fix = findings[0].fix.as_ref().unwrap();
+        assert_eq!(fix.edits[0].location, findings[0].location);
+        assert_eq!(
+            &source_code[fix.edits[0].location.start..fix.edits[0].location.end],
+            "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+        assert_eq!(
+            fix.edits[0].replacement,
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+        );
+    }
+}
@@ -1,53 +1,403 @@
+use crate::abi::format_bytes;
+use crate::dispatcher::{is_call_to, is_dispatcher, is_literal_value, is_selector_expression};
+use crate::literal_value::{evaluate_literal, U256};
+use std::collections::HashMap;
 use yultsur::visitor::ASTVisitor;
-use yultsur::yul::{Block, Literal, SourceLocation};
+use yultsur::yul::{Block, Expression, FunctionCall, Literal, Statement, Switch};
 
+/// What a literal stands for, derived from where it is used.
 #[derive(Hash, Clone, PartialEq, Eq, Debug)]
 pub enum LiteralKind {
+    /// A function selector, e.g. a `case` label of a dispatcher or a value compared to the
+    /// selector of the calldata.
     Selector,
+    /// The target of a call or another instruction taking an address.
     Address,
+    /// A custom error selector written to memory before a `revert`, either shifted into place
+    /// like `mstore(0, shl(224, 0x08c379a0))` or already left-aligned.
+    ErrorSelector,
+    /// The first topic of `log1` to `log4`.
+    EventTopic,
+    /// The slot of `sload`, `sstore`, `tload` or `tstore`.
+    StorageSlot,
+    /// The shift of `shl`, `shr` or `sar`.
+    ShiftAmount,
+    /// A mask of contiguous bits applied with `and`.
+    Bitmask,
 }
-struct LiteralFinder {
-    pub cursor_location: usize,
-    pub literal_kind: LiteralKind,
-    pub found_literal: Option<Literal>,
+
+/// Returns the 4-byte selector of an error selector value, which is either the whole value,
+/// stored shifted into place, or its first 4 bytes if left-aligned.
+fn error_selector(value: &U256) -> Option<String> {
+    if value.is_zero() {
+        None
+    } else if value.bit_length() <= 32 {
+        Some(format_bytes(&value.0[28..]))
+    } else if value.0[4..].iter().all(|byte| *byte == 0) {
+        Some(format_bytes(&value.0[..4]))
+    } else {
+        None
+    }
+}
+
+/// Returns the value the literal stands for in the canonical form used for lookups: 4 bytes
+/// for selectors, 20 bytes for addresses and 32 bytes for topics and slots. Leading zeros
+/// omitted in the source are restored. `None` if the value does not fit.
+pub fn normalized_value(literal: &Literal, literal_kind: &LiteralKind) -> Option<String> {
+    let value = evaluate_literal(&literal.literal).ok()?;
+    if *literal_kind == LiteralKind::ErrorSelector {
+        return error_selector(&value);
+    }
+    let size = match literal_kind {
+        LiteralKind::Selector => 4,
+        LiteralKind::Address => 20,
+        LiteralKind::EventTopic | LiteralKind::StorageSlot => 32,
+        _ => return None,
+    };
+    if value.bit_length() > size * 8 {
+        return None;
+    }
+    Some(format_bytes(&value.0[32 - size..]))
+}
+
+/// Matches `mstore(offset, shl(224, selector))` and `mstore(offset, selector)` with a
+/// left-aligned selector.
+fn stored_error_selector(expression: &Expression) -> Option<&Literal> {
+    let function_call = is_call_to(expression, "mstore")?;
+    let value = function_call.arguments.get(1)?;
+    let (literal, shifted) = match is_call_to(value, "shl") {
+        Some(shift) if shift.arguments.len() == 2 => {
+            if !is_literal_value(&shift.arguments[0], U256::from_u64(224)) {
+                return None;
+            }
+            match &shift.arguments[1] {
+                Expression::Literal(literal) => (literal, true),
+                _ => return None,
+            }
+        }
+        Some(_) => return None,
+        None => match value {
+            Expression::Literal(literal) => (literal, false),
+            _ => return None,
+        },
+    };
+    let value = evaluate_literal(&literal.literal).ok()?;
+    // Only a shifted selector may fit in 4 bytes, otherwise it ends up in the wrong place.
+    if shifted != (value.bit_length() <= 32) {
+        return None;
+    }
+    error_selector(&value).map(|_| literal)
+}
+
+/// Collects error selectors stored to memory in a block that later reverts.
+struct ErrorSelectorCollector {
+    pub found_literals: Vec<Literal>,
+}
+
+impl ASTVisitor for ErrorSelectorCollector {
+    fn visit_block(&mut self, block: &Block) {
+        let mut stored_selectors = vec![];
+        for statement in &block.statements {
+            if let Statement::Expression(expression) = statement {
+                if let Some(literal) = stored_error_selector(expression) {
+                    stored_selectors.push(literal.clone());
+                } else if is_call_to(expression, "revert").is_some() {
+                    self.found_literals.append(&mut stored_selectors);
+                }
+            }
+            self.visit_statement(statement);
+        }
+    }
 }
 
-impl LiteralFinder {
-    pub fn new(cursor_location: usize, literal_kind: LiteralKind) -> LiteralFinder {
-        LiteralFinder {
-            cursor_location,
-            literal_kind,
-            found_literal: None,
+/// Returns the kind of the argument at the index, as far as the called instruction tells.
+fn argument_kind(function_call: &FunctionCall, index: usize) -> Option<LiteralKind> {
+    let arguments = &function_call.arguments;
+    match (function_call.function.name.as_str(), index) {
+        ("call" | "callcode" | "delegatecall" | "staticcall", 1) => Some(LiteralKind::Address),
+        ("balance" | "extcodesize" | "extcodehash" | "extcodecopy" | "selfdestruct", 0) => {
+            Some(LiteralKind::Address)
+        }
+        ("sload" | "sstore" | "tload" | "tstore", 0) => Some(LiteralKind::StorageSlot),
+        ("log1" | "log2" | "log3" | "log4", 2) => Some(LiteralKind::EventTopic),
+        ("shl" | "shr" | "sar", 0) => Some(LiteralKind::ShiftAmount),
+        // A selector shifted into the first 4 bytes, e.g. to encode a call.
+        ("shl", 1) if is_literal_value(&arguments[0], U256::from_u64(224)) => {
+            Some(LiteralKind::Selector)
+        }
+        ("and", 0 | 1) => match &arguments[index] {
+            Expression::Literal(literal) => match evaluate_literal(&literal.literal) {
+                Ok(value) if value.set_bit_range().is_some() => Some(LiteralKind::Bitmask),
+                _ => None,
+            },
+            _ => None,
+        },
+        ("eq", 0 | 1) if arguments.len() == 2 && is_selector_expression(&arguments[1 - index]) => {
+            Some(LiteralKind::Selector)
+        }
+        _ => None,
+    }
+}
+
+/// Fallback for literals whose usage says nothing about them, e.g. values assigned to
+/// variables. The address mask `0xff…ff` has the length of an address, but is none.
+fn kind_from_length(literal: &Literal) -> Option<LiteralKind> {
+    match literal.literal.len() {
+        10 => Some(LiteralKind::Selector),
+        42 => match evaluate_literal(&literal.literal) {
+            Ok(value) if value.bit_length() == 160 && value.set_bit_range().is_some() => {
+                Some(LiteralKind::Bitmask)
+            }
+            _ => Some(LiteralKind::Address),
+        },
+        _ => None,
+    }
+}
+
+/// Classifies all literals by their syntactic context. Literals that are used in a known
+/// context get the kind of that context. Others are classified by their length.
+struct LiteralClassifier {
+    // Kinds determined by the context, keyed by the start of the literal.
+    context_kinds: HashMap<usize, LiteralKind>,
+    pub classified_literals: Vec<(Literal, LiteralKind)>,
+}
+
+impl LiteralClassifier {
+    fn set_kind(&mut self, literal: &Literal, literal_kind: LiteralKind) {
+        if let Some(location) = &literal.location {
+            // Kinds set earlier come from more specific contexts.
+            self.context_kinds
+                .entry(location.start)
+                .or_insert(literal_kind);
+        }
+    }
+}
+
+impl ASTVisitor for LiteralClassifier {
+    fn visit_function_call(&mut self, function_call: &FunctionCall) {
+        for (index, argument) in function_call.arguments.iter().enumerate() {
+            if let Expression::Literal(literal) = argument {
+                if let Some(literal_kind) = argument_kind(function_call, index) {
+                    self.set_kind(literal, literal_kind);
+                }
+            }
+        }
+        for argument in &function_call.arguments {
+            self.visit_expression(argument);
+        }
+    }
+
+    fn visit_switch(&mut self, switch: &Switch) {
+        if is_dispatcher(switch) {
+            for literal in switch.cases.iter().filter_map(|case| case.literal.as_ref()) {
+                self.set_kind(literal, LiteralKind::Selector);
+            }
+        }
+
+        self.visit_expression(&switch.expression);
+        for case in &switch.cases {
+            self.visit_case(case);
         }
     }
 
-    pub fn between(&self, location: &SourceLocation) -> bool {
-        location.start <= self.cursor_location && self.cursor_location < location.end
+    fn visit_literal(&mut self, literal: &Literal) {
+        let context_kind = literal
+            .location
+            .as_ref()
+            .and_then(|location| self.context_kinds.get(&location.start).cloned());
+        if let Some(literal_kind) = context_kind.or_else(|| kind_from_length(literal)) {
+            self.classified_literals
+                .push((literal.clone(), literal_kind));
+        }
     }
 }
 
+/// Returns all literals with a kind, in the order they appear in the source.
+pub fn classify_literals(ast: &Block) -> Vec<(Literal, LiteralKind)> {
+    let mut error_selector_collector = ErrorSelectorCollector {
+        found_literals: vec![],
+    };
+    error_selector_collector.visit_block(ast);
+
+    let mut literal_classifier = LiteralClassifier {
+        context_kinds: HashMap::new(),
+        classified_literals: vec![],
+    };
+    for literal in &error_selector_collector.found_literals {
+        literal_classifier.set_kind(literal, LiteralKind::ErrorSelector);
+    }
+    literal_classifier.visit_block(ast);
+    literal_classifier.classified_literals
+}
+
+/// Returns all literals of the given kind, in the order they appear in the source.
+pub fn collect_literals(ast: &Block, literal_kind: LiteralKind) -> Vec<Literal> {
+    classify_literals(ast)
+        .into_iter()
+        .filter(|(_, kind)| *kind == literal_kind)
+        .map(|(literal, _)| literal)
+        .collect()
+}
+
+/// Returns the literal at the cursor along with its kind.
+pub fn literal_at(ast: &Block, cursor_position: usize) -> Option<(Literal, LiteralKind)> {
+    classify_literals(ast).into_iter().find(|(literal, _)| {
+        literal.location.as_ref().map_or(false, |location| {
+            location.start <= cursor_position && cursor_position < location.end
+        })
+    })
+}
+
+struct LiteralFinder {
+    pub cursor_location: usize,
+    pub found_literal: Option<Literal>,
+}
+
 impl ASTVisitor for LiteralFinder {
     fn visit_literal(&mut self, literal: &Literal) {
         if let Some(location) = &literal.location {
-            // TODO: More strict heuristics
-            if self.between(location)
-                && self.literal_kind == LiteralKind::Selector
-                && literal.literal.len() == 10
-                || self.literal_kind == LiteralKind::Address && literal.literal.len() == 42
-            {
-                assert!(self.found_literal.is_none());
+            if location.start <= self.cursor_location && self.cursor_location < location.end {
                 self.found_literal = Some(literal.clone());
             }
         }
     }
 }
 
+/// Returns the literal at the cursor, whether it has a kind or not.
+pub fn find_any_literal(ast: &Block, cursor_position: usize) -> Option<Literal> {
+    let mut literal_finder = LiteralFinder {
+        cursor_location: cursor_position,
+        found_literal: None,
+    };
+    literal_finder.visit_block(ast);
+    literal_finder.found_literal
+}
+
+/// Returns the literal at the cursor if it is of the given kind.
 pub fn find_literal(
     ast: &Block,
     cursor_position: usize,
     literal_kind: LiteralKind,
 ) -> Option<Literal> {
-    let mut literal_finder = LiteralFinder::new(cursor_position, literal_kind);
-    literal_finder.visit_block(ast);
-    literal_finder.found_literal
+    literal_at(ast, cursor_position)
+        .filter(|(_, kind)| *kind == literal_kind)
+        .map(|(literal, _)| literal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use yultsur::yul_parser::parse_block;
+
+    fn kinds(source_code: &str) -> Vec<(String, LiteralKind)> {
+        let ast = parse_block(source_code).unwrap();
+        classify_literals(&ast)
+            .into_iter()
+            .map(|(literal, literal_kind)| (literal.literal, literal_kind))
+            .collect()
+    }
+
+    #[test]
+    fn error_selectors() {
+        let source_code = indoc! {"
+            {
+                if iszero(callvalue()) {
+                    mstore(0, shl(224, 0x08c379a0))
+                    mstore(4, 0x20)
+                    revert(0, 0x64)
+                }
+                mstore(0, 0x4e487b7100000000000000000000000000000000000000000000000000000000)
+                mstore(4, 0x11)
+                mstore(0x24, shl(192, 0x12345678))
+                revert(0, 0x24)
+                mstore(0, shl(224, 0xa9059cbb))
+                return(0, 4)
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let selectors: Vec<String> = collect_literals(&ast, LiteralKind::ErrorSelector)
+            .iter()
+            .filter_map(|literal| normalized_value(literal, &LiteralKind::ErrorSelector))
+            .collect();
+
+        assert_eq!(selectors, vec!["0x08c379a0", "0x4e487b71"]);
+        assert!(find_literal(
+            &ast,
+            source_code.find("0x08c379a0").unwrap(),
+            LiteralKind::ErrorSelector
+        )
+        .is_some());
+        assert!(find_literal(
+            &ast,
+            source_code.find("0xa9059cbb").unwrap(),
+            LiteralKind::ErrorSelector
+        )
+        .is_none());
+        assert!(find_literal(
+            &ast,
+            source_code.find("0xa9059cbb").unwrap(),
+            LiteralKind::Selector
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn usage_contexts() {
+        let source_code = indoc! {"
+            {
+                switch shr(224, calldataload(0))
+                case 0x6fdde03 {
+                    let ok := staticcall(gas(), 0xdead, 0, 0, 0, 0)
+                    let owner := and(sload(0), 0xffffffffffffffffffffffffffffffffffffffff)
+                    log1(0, 0, 0x1)
+                }
+                if eq(shr(224, calldataload(0)), 0x18160ddd) {}
+                let router := 0xe592427a0aece92de3edee1f18e0157c05861564
+                let data := 0x12
+                let mask := 0xffffffffffffffffffffffffffffffffffffffff
+            }
+        "};
+
+        assert_eq!(
+            kinds(source_code),
+            vec![
+                ("224".to_string(), LiteralKind::ShiftAmount),
+                ("0x6fdde03".to_string(), LiteralKind::Selector),
+                ("0xdead".to_string(), LiteralKind::Address),
+                ("0".to_string(), LiteralKind::StorageSlot),
+                (
+                    "0xffffffffffffffffffffffffffffffffffffffff".to_string(),
+                    LiteralKind::Bitmask
+                ),
+                ("0x1".to_string(), LiteralKind::EventTopic),
+                ("224".to_string(), LiteralKind::ShiftAmount),
+                ("0x18160ddd".to_string(), LiteralKind::Selector),
+                (
+                    "0xe592427a0aece92de3edee1f18e0157c05861564".to_string(),
+                    LiteralKind::Address
+                ),
+                (
+                    "0xffffffffffffffffffffffffffffffffffffffff".to_string(),
+                    LiteralKind::Bitmask
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn normalized_values() {
+        let ast = parse_block("{ pop(staticcall(gas(), 0x1, 0, 0, 0, 0)) }").unwrap();
+        let literal = collect_literals(&ast, LiteralKind::Address).remove(0);
+
+        assert_eq!(
+            normalized_value(&literal, &LiteralKind::Address),
+            Some("0x0000000000000000000000000000000000000001".to_string())
+        );
+        assert_eq!(
+            normalized_value(&literal, &LiteralKind::Selector),
+            Some("0x00000001".to_string())
+        );
+        assert_eq!(normalized_value(&literal, &LiteralKind::Bitmask), None);
+    }
 }
@@ -0,0 +1,214 @@
+use crate::literal_finder::LiteralKind;
+use crate::literal_value::{evaluate_literal, literal_bytes, to_hex, U256};
+
+/// Values from 1 gwei up to a billion ether are shown in ether units. Larger numbers are
+/// rarely amounts, e.g. hashes.
+const MIN_AMOUNT_DIGITS: usize = 10;
+const MAX_AMOUNT_DIGITS: usize = 27;
+
+fn binary_string(value: &U256) -> String {
+    let bits: String = value.0.iter().map(|byte| format!("{:08b}", byte)).collect();
+    let bits = bits.trim_start_matches('0');
+    if bits.is_empty() {
+        return "0b0".to_string();
+    }
+    // Grouped by bytes, counting from the least significant bit.
+    let first_group = match bits.len() % 8 {
+        0 => 8,
+        length => length,
+    };
+    let mut groups = vec![&bits[..first_group]];
+    groups.extend(
+        (first_group..bits.len())
+            .step_by(8)
+            .map(|start| &bits[start..start + 8]),
+    );
+    format!("0b{}", groups.join("_"))
+}
+
+/// Describes a value made of contiguous set bits, like `0xff` or `0xff00`. Masks narrower
+/// than a byte are only described if the value is known to be used as a mask, since small
+/// numbers like `1` or `3` are rarely masks.
+fn mask_description(value: &U256, is_bitmask: bool) -> Option<String> {
+    let (shift, width) = value.set_bit_range()?;
+    if width < 8 && !is_bitmask {
+        return None;
+    }
+
+    let type_note = match width {
+        160 => " (`uint160`, an address)".to_string(),
+        _ if width % 8 == 0 => format!(" (`uint{}`)", width),
+        _ => "".to_string(),
+    };
+    Some(if shift == 0 {
+        format!("Mask of the lowest {} bits{}", width, type_note)
+    } else if shift + width == 256 {
+        format!("Mask of the highest {} bits{}", width, type_note)
+    } else {
+        format!("Mask of {} bits shifted by {}{}", width, shift, type_note)
+    })
+}
+
+/// Formats an amount of wei in a larger unit with the given number of decimals.
+fn format_units(wei: &str, decimals: usize) -> String {
+    let padded = format!("{:0>width$}", wei, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+/// Returns the bytes as text if they are printable ASCII, ignoring zero padding on the right.
+fn printable_ascii(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().rposition(|byte| *byte != 0)? + 1;
+    let text = &bytes[..end];
+    if text.len() >= 2 && text.iter().all(|byte| (0x20..0x7f).contains(byte)) {
+        Some(String::from_utf8_lossy(text).to_string())
+    } else {
+        None
+    }
+}
+
+fn describe_number(text: &str, value: &U256, is_bitmask: bool) -> String {
+    let decimal = value.to_decimal_string();
+    let mut lines = vec![
+        format!("- Decimal: `{}`", decimal),
+        format!("- Hex: `{}`", value.to_hex_string()),
+        format!("- Binary: `{}`", binary_string(value)),
+        format!("- Bit length: {}", value.bit_length()),
+    ];
+
+    let mask = mask_description(value, is_bitmask);
+    if let Some(mask) = &mask {
+        lines.push(format!("- {}", mask));
+    }
+    if mask.is_none() && (MIN_AMOUNT_DIGITS..=MAX_AMOUNT_DIGITS).contains(&decimal.len()) {
+        lines.push(format!(
+            "- As wei: {} gwei = {} ether",
+            format_units(&decimal, 9),
+            format_units(&decimal, 18)
+        ));
+    }
+    if text.starts_with("0x") {
+        if let Some(ascii) = printable_ascii(value.significant_bytes()) {
+            lines.push(format!("- ASCII: `\"{}\"`", ascii));
+        }
+    }
+    format!("Number `{}`\n\n{}", text, lines.join("\n"))
+}
+
+fn describe_string(text: &str, bytes: &[u8]) -> String {
+    let mut padded = bytes.to_vec();
+    padded.resize(32, 0);
+    let mut description = format!(
+        "String literal of {} bytes\n\n- As `bytes32`: `0x{}`",
+        bytes.len(),
+        to_hex(&padded)
+    );
+    if text.starts_with("hex") {
+        if let Some(ascii) = printable_ascii(bytes) {
+            description.push_str(&format!("\n- ASCII: `\"{}\"`", ascii));
+        }
+    }
+    description
+}
+
+/// Describes the value of a number or string literal in Markdown, given the kind of the
+/// literal if known. `None` if the literal is invalid.
+pub fn describe_literal(text: &str, literal_kind: Option<&LiteralKind>) -> Option<String> {
+    match literal_bytes(text) {
+        Some(bytes) => {
+            let bytes = bytes.ok()?;
+            if bytes.len() > 32 {
+                return None;
+            }
+            Some(describe_string(text, &bytes))
+        }
+        None => {
+            let value = evaluate_literal(text).ok()?;
+            Some(describe_number(
+                text,
+                &value,
+                literal_kind == Some(&LiteralKind::Bitmask),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(
+            describe_literal("0x0de0b6b3a7640000", None).unwrap(),
+            indoc! {"
+                Number `0x0de0b6b3a7640000`
+
+                - Decimal: `1000000000000000000`
+                - Hex: `0xde0b6b3a7640000`
+                - Binary: `0b1101_11100000_10110110_10110011_10100111_01100100_00000000_00000000`
+                - Bit length: 60
+                - As wei: 1000000000 gwei = 1 ether"
+            }
+        );
+        assert_eq!(
+            describe_literal("1500000000", None).unwrap().lines().last(),
+            Some("- As wei: 1.5 gwei = 0.0000000015 ether")
+        );
+        assert_eq!(
+            describe_literal(&format!("0x{}", "ff".repeat(20)), None)
+                .unwrap()
+                .lines()
+                .last(),
+            Some("- Mask of the lowest 160 bits (`uint160`, an address)")
+        );
+        assert_eq!(
+            describe_literal(
+                "0xffffffff00000000000000000000000000000000000000000000000000000000",
+                None
+            )
+            .unwrap()
+            .lines()
+            .last(),
+            Some("- Mask of the highest 32 bits (`uint32`)")
+        );
+        assert_eq!(
+            describe_literal("0x48656c6c6f", None)
+                .unwrap()
+                .lines()
+                .last(),
+            Some("- ASCII: `\"Hello\"`")
+        );
+        assert_eq!(
+            describe_literal("0x7", Some(&LiteralKind::Bitmask))
+                .unwrap()
+                .lines()
+                .last(),
+            Some("- Mask of the lowest 3 bits")
+        );
+        assert!(!describe_literal("0x7", None).unwrap().contains("Mask"));
+        assert_eq!(describe_literal("0x1g", None), None);
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            describe_literal("\"abc\"", None).unwrap(),
+            format!(
+                "String literal of 3 bytes\n\n- As `bytes32`: `0x616263{}`",
+                "00".repeat(29)
+            )
+        );
+        assert_eq!(
+            describe_literal("hex\"4869\"", None)
+                .unwrap()
+                .lines()
+                .last(),
+            Some("- ASCII: `\"Hi\"`")
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::fmt;
+
+/// Maximum length of a string literal. Its value must fit in a single stack slot.
+pub const MAX_STRING_LENGTH: usize = 32;
+
+/// A 256-bit EVM word, stored big-endian.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct U256(pub [u8; 32]);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LiteralError {
+    NumberTooLarge,
+    StringTooLong(usize),
+    MalformedNumber,
+    MalformedHex,
+    InvalidEscape,
+}
+
+impl fmt::Display for LiteralError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LiteralError::NumberTooLarge => {
+                write!(f, "Number literal exceeds the maximum value of 2^256-1.")
+            }
+            LiteralError::StringTooLong(length) => write!(
+                f,
+                "String literal is {} bytes long. The maximum is {} bytes.",
+                length, MAX_STRING_LENGTH
+            ),
+            LiteralError::MalformedNumber => write!(f, "Malformed number literal."),
+            LiteralError::MalformedHex => write!(f, "Malformed hex literal."),
+            LiteralError::InvalidEscape => write!(f, "Invalid escape sequence in string literal."),
+        }
+    }
+}
+
+impl U256 {
+    pub fn from_u64(value: u64) -> U256 {
+        let mut bytes = [0; 32];
+        bytes[24..].copy_from_slice(&value.to_be_bytes());
+        U256(bytes)
+    }
+
+    /// Interprets up to 32 bytes as a big-endian number.
+    pub fn from_be_slice(slice: &[u8]) -> Option<U256> {
+        if slice.len() > 32 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        bytes[32 - slice.len()..].copy_from_slice(slice);
+        Some(U256(bytes))
+    }
+
+    /// Left-aligns up to 32 bytes in a word, the way string literals are stored.
+    pub fn from_left_aligned(slice: &[u8]) -> Option<U256> {
+        if slice.len() > 32 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        bytes[..slice.len()].copy_from_slice(slice);
+        Some(U256(bytes))
+    }
+
+    pub fn from_str_radix(digits: &str, radix: u32) -> Result<U256, LiteralError> {
+        if digits.is_empty() {
+            return Err(LiteralError::MalformedNumber);
+        }
+
+        let mut value = U256::default();
+        for c in digits.chars() {
+            let digit = c.to_digit(radix).ok_or(LiteralError::MalformedNumber)?;
+            if value.mul_add(radix, digit) {
+                return Err(LiteralError::NumberTooLarge);
+            }
+        }
+        Ok(value)
+    }
+
+    /// Computes `self * factor + addend` in place. Returns true on overflow.
+    fn mul_add(&mut self, factor: u32, addend: u32) -> bool {
+        let mut carry = addend as u64;
+        for byte in self.0.iter_mut().rev() {
+            let product = *byte as u64 * factor as u64 + carry;
+            *byte = product as u8;
+            carry = product >> 8;
+        }
+        carry != 0
+    }
+
+    fn div_rem(&self, divisor: u32) -> (U256, u32) {
+        let mut quotient = [0; 32];
+        let mut remainder = 0_u64;
+        for (index, byte) in self.0.iter().enumerate() {
+            let current = (remainder << 8) | *byte as u64;
+            quotient[index] = (current / divisor as u64) as u8;
+            remainder = current % divisor as u64;
+        }
+        (U256(quotient), remainder as u32)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|byte| *byte == 0)
+    }
+
+    pub fn bit_length(&self) -> usize {
+        match self.0.iter().position(|byte| *byte != 0) {
+            Some(index) => (32 - index) * 8 - self.0[index].leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    /// Returns the position of the lowest set bit and the number of set bits if they are
+    /// contiguous, like in `0x7` or `0xff00`. `None` for zero and values with gaps.
+    pub fn set_bit_range(&self) -> Option<(usize, usize)> {
+        let bits: String = self.0.iter().map(|byte| format!("{:08b}", byte)).collect();
+        let ones_start = bits.find('1')?;
+        let ones_end = bits.rfind('1')? + 1;
+        if bits[ones_start..ones_end].contains('0') {
+            return None;
+        }
+        Some((256 - ones_end, ones_end - ones_start))
+    }
+
+    /// Returns the minimal big-endian representation, without leading zero bytes.
+    pub fn significant_bytes(&self) -> &[u8] {
+        let start = self.0.iter().position(|byte| *byte != 0).unwrap_or(32);
+        &self.0[start..]
+    }
+
+    pub fn to_decimal_string(&self) -> String {
+        if self.is_zero() {
+            return "0".to_string();
+        }
+
+        let mut digits = vec![];
+        let mut value = *self;
+        while !value.is_zero() {
+            let (quotient, remainder) = value.div_rem(10);
+            digits.push(char::from_digit(remainder, 10).unwrap());
+            value = quotient;
+        }
+        digits.iter().rev().collect()
+    }
+
+    /// Returns the value in hex, with `0x` prefix and without leading zeros.
+    pub fn to_hex_string(&self) -> String {
+        let hex = to_hex(self.significant_bytes());
+        match hex.trim_start_matches('0') {
+            "" => "0x0".to_string(),
+            digits => format!("0x{}", digits),
+        }
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn parse_hex(digits: &str) -> Option<Vec<u8>> {
+    if digits.len() % 2 != 0 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&digits[index..index + 2], 16).ok())
+        .collect()
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    let quote = text.chars().next()?;
+    if (quote == '"' || quote == '\'') && text.len() >= 2 && text.ends_with(quote) {
+        Some(&text[1..text.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn decode_string(content: &str) -> Result<Vec<u8>, LiteralError> {
+    let mut bytes = vec![];
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        match chars.next().ok_or(LiteralError::InvalidEscape)? {
+            '\\' => bytes.push(b'\\'),
+            '\'' => bytes.push(b'\''),
+            '"' => bytes.push(b'"'),
+            'n' => bytes.push(b'\n'),
+            'r' => bytes.push(b'\r'),
+            't' => bytes.push(b'\t'),
+            '\n' => {}
+            'x' => {
+                let digits: String = chars.by_ref().take(2).collect();
+                let decoded = parse_hex(&digits).ok_or(LiteralError::InvalidEscape)?;
+                bytes.extend(decoded);
+            }
+            'u' => {
+                let digits: String = chars.by_ref().take(4).collect();
+                let code_point = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(|_| digits.len() == 4)
+                    .and_then(char::from_u32)
+                    .ok_or(LiteralError::InvalidEscape)?;
+                let mut buffer = [0; 4];
+                bytes.extend_from_slice(code_point.encode_utf8(&mut buffer).as_bytes());
+            }
+            _ => return Err(LiteralError::InvalidEscape),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns the bytes of a string or hex string literal, or None if it is a number literal.
+pub fn literal_bytes(text: &str) -> Option<Result<Vec<u8>, LiteralError>> {
+    if let Some(content) = text.strip_prefix("hex").and_then(unquote) {
+        let digits = content.replace('_', "");
+        return Some(parse_hex(&digits).ok_or(LiteralError::MalformedHex));
+    }
+    unquote(text).map(decode_string)
+}
+
+/// Computes the value of a Yul literal, the way it would be placed on the stack.
+pub fn evaluate_literal(text: &str) -> Result<U256, LiteralError> {
+    if let Some(bytes) = literal_bytes(text) {
+        let bytes = bytes?;
+        return U256::from_left_aligned(&bytes).ok_or(LiteralError::StringTooLong(bytes.len()));
+    }
+
+    match text {
+        "true" => Ok(U256::from_u64(1)),
+        "false" => Ok(U256::default()),
+        _ => match text.strip_prefix("0x") {
+            Some(digits) => U256::from_str_radix(digits, 16).map_err(|error| match error {
+                LiteralError::MalformedNumber => LiteralError::MalformedHex,
+                error => error,
+            }),
+            None => U256::from_str_radix(text, 10),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers() {
+        assert_eq!(evaluate_literal("0"), Ok(U256::default()));
+        assert_eq!(evaluate_literal("0x20"), Ok(U256::from_u64(32)));
+        assert_eq!(evaluate_literal("1234"), Ok(U256::from_u64(1234)));
+        assert_eq!(
+            evaluate_literal(&format!("0x{}", "ff".repeat(32))),
+            Ok(U256([0xff; 32]))
+        );
+        assert_eq!(
+            evaluate_literal(&format!("0x1{}", "00".repeat(32))),
+            Err(LiteralError::NumberTooLarge)
+        );
+        assert_eq!(
+            evaluate_literal(
+                "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+            ),
+            Err(LiteralError::NumberTooLarge)
+        );
+        assert_eq!(evaluate_literal("0x"), Err(LiteralError::MalformedHex));
+        assert_eq!(evaluate_literal("0x1g"), Err(LiteralError::MalformedHex));
+    }
+
+    #[test]
+    fn strings() {
+        let mut expected = [0; 32];
+        expected[..3].copy_from_slice(b"a\nb");
+        assert_eq!(evaluate_literal("\"a\\nb\""), Ok(U256(expected)));
+        assert_eq!(
+            evaluate_literal(&format!("\"{}\"", "a".repeat(33))),
+            Err(LiteralError::StringTooLong(33))
+        );
+        assert_eq!(
+            evaluate_literal("\"\\q\""),
+            Err(LiteralError::InvalidEscape)
+        );
+        assert_eq!(
+            evaluate_literal("hex\"0a_0b\"").unwrap().0[..2],
+            [0x0a, 0x0b]
+        );
+        assert_eq!(
+            evaluate_literal("hex\"abc\""),
+            Err(LiteralError::MalformedHex)
+        );
+    }
+
+    #[test]
+    fn formatting() {
+        let value = evaluate_literal("0xa9059cbb").unwrap();
+
+        assert_eq!(value.to_decimal_string(), "2835717307");
+        assert_eq!(value.to_hex_string(), "0xa9059cbb");
+        assert_eq!(value.bit_length(), 32);
+        assert_eq!(U256::default().to_hex_string(), "0x0");
+        assert_eq!(U256::default().bit_length(), 0);
+    }
+
+    #[test]
+    fn bit_ranges() {
+        assert_eq!(U256::from_u64(0x7).set_bit_range(), Some((0, 3)));
+        assert_eq!(U256::from_u64(0xff00).set_bit_range(), Some((8, 8)));
+        assert_eq!(U256([0xff; 32]).set_bit_range(), Some((0, 256)));
+        assert_eq!(U256::from_u64(0x5).set_bit_range(), None);
+        assert_eq!(U256::default().set_bit_range(), None);
+    }
+}
@@ -1,21 +1,150 @@
+use crate::abi_artifacts::EventSignature;
+use crate::address::{checksum_status, ChecksumStatus};
+use crate::address_book::AddressEntry;
+use crate::config::{default_config_path, Settings};
 use crate::definition_finder::find_definition;
-use crate::literal_finder::{find_literal, LiteralKind};
+use crate::diagnostics::{analyze, Finding, Severity};
+use crate::dispatcher::SignatureLookup;
+use crate::dune_apis::DuneConfig;
+use crate::event_checker::{find_event_topic, EventLookup, EventTopic};
+use crate::known_addresses::known_address;
+use crate::literal_finder::{
+    classify_literals, find_any_literal, literal_at, normalized_value, LiteralKind,
+};
+use crate::literal_hover::describe_literal;
+use crate::name_cache::default_cache_path;
+use crate::name_lookup::NameLookup;
+use crate::storage_slots::describe_storage_slot;
 use dashmap::DashMap;
 use ropey::Rope;
+use serde_json::Value;
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::Duration;
+use tokio::task::JoinHandle;
 use tower_lsp::jsonrpc::{Error, ErrorCode, Result};
 use tower_lsp::lsp_types::*;
 use tower_lsp::{Client, LanguageServer};
+use yultsur::dialect::EVMDialect;
+use yultsur::resolver::resolve;
+use yultsur::yul::{Block, SourceLocation};
 use yultsur::yul_parser::parse_block;
 
+pub const CLEAR_CACHE_COMMAND: &str = "yul-lsp.clearCache";
+pub const INSPECT_CACHE_COMMAND: &str = "yul-lsp.inspectCache";
+
+/// Numbers the progress tokens of prefetches.
+static PREFETCH_COUNTER: AtomicU64 = AtomicU64::new(0);
+/// How long the text must stay unchanged before the names in it are prefetched, so that
+/// literals still being typed are not looked up.
+const PREFETCH_DELAY: Duration = Duration::from_millis(1500);
+
 #[derive(Debug)]
 pub struct Backend {
     pub client: Client,
+    pub name_lookup: NameLookup,
     pub document_map: DashMap<String, Rope>,
+    pub finding_map: DashMap<String, Vec<Finding>>,
+    /// Whether the client accepts `$/progress` notifications created by the server.
+    pub work_done_progress: AtomicBool,
+    /// ERC-7201 namespace ids from the settings.
+    pub storage_namespaces: RwLock<Vec<String>>,
+    /// Pending or running prefetch of each document.
+    pub prefetch_tasks: DashMap<String, JoinHandle<()>>,
+}
+
+/// Releases the keys of a prefetch and ends its progress report, also if the prefetch is
+/// cancelled.
+struct PrefetchGuard {
+    name_lookup: NameLookup,
+    keys: Vec<String>,
+    progress: Option<(Client, NumberOrString)>,
+}
+
+impl Drop for PrefetchGuard {
+    fn drop(&mut self) {
+        for key in &self.keys {
+            self.name_lookup.finish_lookup(key);
+        }
+        if let (Some((client, token)), Ok(runtime)) =
+            (self.progress.take(), tokio::runtime::Handle::try_current())
+        {
+            runtime.spawn(async move {
+                client
+                    .send_notification::<notification::Progress>(ProgressParams {
+                        token,
+                        value: ProgressParamsValue::WorkDone(WorkDoneProgress::End(
+                            WorkDoneProgressEnd { message: None },
+                        )),
+                    })
+                    .await;
+            });
+        }
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
-    async fn initialize(&self, _: InitializeParams) -> Result<InitializeResult> {
+    async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        let config_path = default_config_path();
+        let config_file = config_path
+            .as_deref()
+            .and_then(|path| Some((path, read_to_string(path).ok()?)));
+        let settings = Settings::from_sources(
+            params.initialization_options,
+            config_file
+                .as_ref()
+                .map(|(path, content)| (*path, content.as_str())),
+        );
+        let mut settings = match settings {
+            Ok(settings) => settings,
+            Err(error) => {
+                self.client
+                    .log_message(MessageType::ERROR, format!("Invalid settings: {}", error))
+                    .await;
+                Settings::default()
+            }
+        };
+        let work_done_progress = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|window| window.work_done_progress)
+            .unwrap_or(false);
+        self.work_done_progress
+            .store(work_done_progress, Ordering::Relaxed);
+        if let Some(root) = params.root_uri.and_then(|root| root.to_file_path().ok()) {
+            settings.resolve_paths(&root);
+        }
+        let errors = self
+            .name_lookup
+            .load_signature_files(&settings.signature_files);
+        self.log_load_errors("signatures", errors).await;
+        let artifact_paths = settings.artifact_paths.clone();
+        let errors = self
+            .load_blocking(move |name_lookup| name_lookup.load_artifacts(&artifact_paths))
+            .await;
+        self.log_load_errors("ABI artifacts", errors).await;
+        let address_book_paths = settings.address_book_paths.clone();
+        let errors = self
+            .load_blocking(move |name_lookup| name_lookup.load_address_book(&address_book_paths))
+            .await;
+        self.log_load_errors("addresses", errors).await;
+        self.name_lookup
+            .set_cache_path(if settings.persistent_cache {
+                settings.cache_file.clone().or_else(default_cache_path)
+            } else {
+                None
+            });
+        self.name_lookup.set_resolvers(
+            &settings.resolvers,
+            settings.chain_id,
+            DuneConfig::load(&settings.dune),
+        );
+        *self.storage_namespaces.write().unwrap() = settings.storage_namespaces;
+
         Ok(InitializeResult {
             server_info: None,
             capabilities: ServerCapabilities {
@@ -24,6 +153,15 @@ impl LanguageServer for Backend {
                 )),
                 definition_provider: Some(OneOf::Left(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![
+                        CLEAR_CACHE_COMMAND.to_string(),
+                        INSPECT_CACHE_COMMAND.to_string(),
+                    ],
+                    ..ExecuteCommandOptions::default()
+                }),
                 ..ServerCapabilities::default()
             },
         })
@@ -33,6 +171,41 @@ impl LanguageServer for Backend {
         self.client
             .log_message(MessageType::INFO, "initialized!")
             .await;
+
+        // Artifacts change whenever the project is rebuilt, deployments whenever it is deployed.
+        let watchers = self
+            .name_lookup
+            .artifact_paths()
+            .iter()
+            .chain(self.name_lookup.address_book_paths().iter())
+            .map(|path| FileSystemWatcher {
+                glob_pattern: if path.extension().is_some() {
+                    path.display().to_string()
+                } else {
+                    format!("{}/**/*.json", path.display())
+                },
+                kind: None,
+            })
+            .collect::<Vec<_>>();
+        if watchers.is_empty() {
+            return;
+        }
+        let registration = Registration {
+            id: "project-file-watcher".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        };
+        if let Err(error) = self.client.register_capability(vec![registration]).await {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Failed to watch project files: {}", error),
+                )
+                .await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -47,32 +220,78 @@ impl LanguageServer for Backend {
             .text_document
             .uri
             .clone();
-        let rope = self.document_map.get(&uri.to_string()).unwrap();
+        // Not holding the map entry while waiting for remote lookups.
+        let rope = self.document_map.get(&uri.to_string()).unwrap().clone();
 
         let position = params.text_document_position_params.position;
-        let byte_start = rope.try_line_to_byte(position.line as usize).unwrap();
-        let byte_end = rope.try_char_to_byte(position.character as usize).unwrap();
-        let byte_offset = byte_start + byte_end as usize;
+        let byte_offset = match position_to_offset(&rope, position) {
+            Some(byte_offset) => byte_offset,
+            None => return Err(Error::new(ErrorCode::InvalidParams)),
+        };
         let source = rope.to_string();
         match parse_block(&source) {
             Err(_) => Err(Error::new(ErrorCode::ParseError)),
             Ok(ast) => {
-                if let Some(_literal) = find_literal(&ast, byte_offset, LiteralKind::Selector) {
-                    let tooltip = "selector";
-                    Ok(Some(Hover {
-                        contents: HoverContents::Scalar(MarkedString::String(tooltip.to_string())),
-                        range: None,
-                    }))
-                } else if let Some(_literal) = find_literal(&ast, byte_offset, LiteralKind::Address)
-                {
-                    let tooltip = "address";
-                    Ok(Some(Hover {
-                        contents: HoverContents::Scalar(MarkedString::String(tooltip.to_string())),
-                        range: None,
-                    }))
-                } else {
-                    Ok(None)
-                }
+                let (literal, literal_kind, tooltip) = match literal_at(&ast, byte_offset) {
+                    Some((literal, literal_kind)) => {
+                        let value = normalized_value(&literal, &literal_kind);
+                        let tooltip = match (&literal_kind, value) {
+                            (LiteralKind::EventTopic, _) => find_event_topic(&ast, byte_offset)
+                                .map(|event_topic| self.event_topic_hover(&event_topic)),
+                            (LiteralKind::ErrorSelector, Some(selector)) => {
+                                Some(self.error_selector_hover(&selector))
+                            }
+                            (LiteralKind::Selector, Some(selector)) => {
+                                Some(self.selector_hover(&selector).await)
+                            }
+                            (LiteralKind::Address, Some(address)) => {
+                                Some(self.address_hover(&literal.literal, &address).await)
+                            }
+                            (LiteralKind::StorageSlot, Some(slot)) => describe_storage_slot(
+                                &slot,
+                                &self.storage_namespaces.read().unwrap(),
+                            ),
+                            _ => None,
+                        };
+                        (Some(literal), Some(literal_kind), tooltip)
+                    }
+                    // Well-known slots are often held in variables, e.g. `let slot := 0x3608…`
+                    // followed by `sload(slot)`.
+                    None => match find_any_literal(&ast, byte_offset) {
+                        Some(literal)
+                            if literal.literal.starts_with("0x") && literal.literal.len() == 66 =>
+                        {
+                            let tooltip = describe_storage_slot(
+                                &literal.literal.to_lowercase(),
+                                &self.storage_namespaces.read().unwrap(),
+                            );
+                            (Some(literal), None, tooltip)
+                        }
+                        _ => (None, None, None),
+                    },
+                };
+                // Literals without a more specific description show their value.
+                let (literal, tooltip) = match (literal, tooltip) {
+                    (Some(literal), Some(tooltip)) => (literal, tooltip),
+                    (literal, _) => match literal.or_else(|| find_any_literal(&ast, byte_offset)) {
+                        Some(literal) => {
+                            match describe_literal(&literal.literal, literal_kind.as_ref()) {
+                                Some(tooltip) => (literal, tooltip),
+                                None => return Ok(None),
+                            }
+                        }
+                        None => return Ok(None),
+                    },
+                };
+                Ok(Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: tooltip,
+                    }),
+                    range: literal
+                        .location
+                        .and_then(|location| location_to_range(&rope, &location)),
+                }))
             }
         }
     }
@@ -124,6 +343,193 @@ impl LanguageServer for Backend {
         }
     }
 
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri;
+        let rope = match self.document_map.get(&uri.to_string()) {
+            Some(rope) => rope,
+            None => return Ok(None),
+        };
+        let findings = match self.finding_map.get(&uri.to_string()) {
+            Some(findings) => findings,
+            None => return Ok(None),
+        };
+
+        let mut actions = vec![];
+        for finding in findings.iter() {
+            let fix = match &finding.fix {
+                Some(fix) => fix,
+                None => continue,
+            };
+            let diagnostic = match finding_to_diagnostic(&uri, &rope, finding) {
+                Some(diagnostic) => diagnostic,
+                None => continue,
+            };
+            if diagnostic.range.end < params.range.start
+                || params.range.end < diagnostic.range.start
+            {
+                continue;
+            }
+
+            let edits = fix
+                .edits
+                .iter()
+                .filter_map(|edit| {
+                    Some(TextEdit::new(
+                        location_to_range(&rope, &edit.location)?,
+                        edit.replacement.clone(),
+                    ))
+                })
+                .collect();
+            actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                title: fix.title.clone(),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(vec![diagnostic]),
+                edit: Some(WorkspaceEdit::new(
+                    [(uri.clone(), edits)].into_iter().collect(),
+                )),
+                is_preferred: Some(true),
+                ..CodeAction::default()
+            }));
+        }
+        Ok(Some(actions))
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri;
+        let rope = match self.document_map.get(&uri.to_string()) {
+            Some(rope) => rope.clone(),
+            None => return Ok(None),
+        };
+        let ast = match parse_block(&rope.to_string()) {
+            Ok(ast) => ast,
+            Err(_) => return Err(Error::new(ErrorCode::ParseError)),
+        };
+
+        self.name_lookup.reload_cache().await;
+        let mut hints = vec![];
+        let mut missing_names = false;
+        for (literal, literal_kind) in classify_literals(&ast) {
+            let position = match literal
+                .location
+                .as_ref()
+                .and_then(|location| offset_to_position(&rope, location.end))
+            {
+                Some(position) => position,
+                None => continue,
+            };
+            if position < params.range.start || params.range.end < position {
+                continue;
+            }
+            let value = match normalized_value(&literal, &literal_kind) {
+                Some(value) => value,
+                None => continue,
+            };
+
+            let label = match literal_kind {
+                LiteralKind::Selector => {
+                    match self.name_lookup.cached_function_signatures(&value) {
+                        Some(signatures) if signatures.is_empty() => continue,
+                        Some(signatures) => signatures.join(" | "),
+                        None => {
+                            missing_names = true;
+                            continue;
+                        }
+                    }
+                }
+                LiteralKind::Address => match known_address(&value) {
+                    Some(known_address) => known_address.name.to_string(),
+                    None => match self.name_lookup.cached_contract_names(&value) {
+                        Some(entries) if entries.is_empty() => continue,
+                        Some(entries) => contract_names_label(&entries),
+                        None => {
+                            missing_names = true;
+                            continue;
+                        }
+                    },
+                },
+                _ => continue,
+            };
+            hints.push(InlayHint {
+                position,
+                label: InlayHintLabel::String(format!("\"{}\"", label)),
+                kind: None,
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            });
+        }
+
+        // Names that are not cached yet are looked up by the prefetch of the document, which
+        // batches them. It asks for the hints again once done.
+        if missing_names && !self.prefetch_pending(&uri) {
+            self.prefetch(&uri, &ast);
+        }
+        Ok(Some(hints))
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<Value>> {
+        match params.command.as_str() {
+            CLEAR_CACHE_COMMAND => {
+                if let Err(error) = self.name_lookup.clear_cache() {
+                    return Err(Error {
+                        code: ErrorCode::InternalError,
+                        message: format!("Failed to clear the cache: {}", error),
+                        data: None,
+                    });
+                }
+                self.client
+                    .show_message(MessageType::INFO, "Name cache cleared.")
+                    .await;
+                refresh_inlay_hints(&self.client).await;
+                Ok(None)
+            }
+            INSPECT_CACHE_COMMAND => {
+                // Optionally takes a selector or address whose entries to show.
+                let key = params.arguments.first().and_then(Value::as_str);
+                Ok(Some(self.name_lookup.inspect_cache(key)))
+            }
+            _ => Err(Error::invalid_params(format!(
+                "Unknown command {}",
+                params.command
+            ))),
+        }
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let changed_paths: Vec<PathBuf> = params
+            .changes
+            .iter()
+            .filter_map(|change| change.uri.to_file_path().ok())
+            .collect();
+        let affects = |paths: Vec<PathBuf>| {
+            changed_paths
+                .iter()
+                .any(|changed_path| paths.iter().any(|path| changed_path.starts_with(path)))
+        };
+        let artifacts_changed = affects(self.name_lookup.artifact_paths());
+        let address_book_changed = affects(self.name_lookup.address_book_paths());
+
+        if artifacts_changed {
+            let errors = self
+                .load_blocking(|name_lookup| name_lookup.reload_artifacts())
+                .await;
+            self.log_load_errors("ABI artifacts", errors).await;
+            // Dispatchers and logs are checked against the signatures of the artifacts.
+            self.analyze_open_documents().await;
+        }
+        if address_book_changed {
+            let errors = self
+                .load_blocking(|name_lookup| name_lookup.reload_address_book())
+                .await;
+            self.log_load_errors("addresses", errors).await;
+        }
+        if artifacts_changed || address_book_changed {
+            refresh_inlay_hints(&self.client).await;
+        }
+    }
+
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         self.client
             .log_message(MessageType::INFO, "file opened!")
@@ -158,6 +564,14 @@ impl LanguageServer for Backend {
 
         self.document_map
             .remove(&params.text_document.uri.to_string());
+        self.finding_map
+            .remove(&params.text_document.uri.to_string());
+        if let Some((_, task)) = self
+            .prefetch_tasks
+            .remove(&params.text_document.uri.to_string())
+        {
+            task.abort();
+        }
     }
 }
 
@@ -166,13 +580,570 @@ pub struct TextDocumentItem {
     text: String,
     // FIXME: We should be using tower_lsp::lsp_types::TextDocumentItem here instead
     // of shadowing it with our own struct.
-    #[allow(dead_code)]
     version: i32,
 }
 
 impl Backend {
+    async fn selector_hover(&self, selector: &str) -> String {
+        let signatures = self.name_lookup.function_signatures(selector).await;
+        match signatures {
+            Ok(signatures) => match signatures.as_slice() {
+                [] => format!("Function selector `{}`\n\nNo known signature.", selector),
+                [signature] => format!("`{}`", signature),
+                _ => format!(
+                    "Function selector `{}` matches multiple signatures:\n\n{}",
+                    selector,
+                    signatures
+                        .iter()
+                        .map(|signature| format!("- `{}`", signature))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            },
+            Err(error) => {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Signature lookup failed: {}", error),
+                    )
+                    .await;
+                format!(
+                    "Function selector `{}`\n\nSignature lookup failed.",
+                    selector
+                )
+            }
+        }
+    }
+
+    fn error_selector_hover(&self, selector: &str) -> String {
+        let signatures = self.name_lookup.local_error_signatures(selector);
+        match signatures.as_slice() {
+            [] => format!("Error selector `{}`\n\nNo known error.", selector),
+            [signature] => format!("Error `{}`{}", signature, standard_error_note(signature)),
+            _ => format!(
+                "Error selector `{}` matches multiple errors:\n\n{}",
+                selector,
+                signatures
+                    .iter()
+                    .map(|signature| format!("- `{}`", signature))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        }
+    }
+
+    fn event_topic_hover(&self, event_topic: &EventTopic) -> String {
+        let events = self.name_lookup.local_event_signatures(&event_topic.topic);
+        let describe = |event: &EventSignature| {
+            if event.anonymous {
+                format!("`{}` (anonymous)", event.signature)
+            } else {
+                format!("`{}` ({} indexed)", event.signature, event.indexed_count)
+            }
+        };
+        match events.as_slice() {
+            [] => format!("Event topic `{}`\n\nNo known event.", event_topic.topic),
+            [event] => format!("Event {}", describe(event)),
+            _ => format!(
+                "Event topic `{}` matches multiple events:\n\n{}",
+                event_topic.topic,
+                events
+                    .iter()
+                    .map(|event| format!("- {}", describe(event)))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ),
+        }
+    }
+
+    /// Describes the address, given as written and normalized to 20 bytes.
+    async fn address_hover(&self, text: &str, address: &str) -> String {
+        let checksum_note = match checksum_status(text) {
+            Some(ChecksumStatus::Invalid { expected }) => format!(
+                "\n\n**Warning:** invalid EIP-55 checksum. Expected `{}`.",
+                expected
+            ),
+            _ => "".to_string(),
+        };
+        if let Some(known_address) = known_address(address) {
+            return format!("{}{}", known_address.describe(), checksum_note);
+        }
+
+        let names = self.name_lookup.contract_names(address).await;
+        match names {
+            Ok(names) => match names.as_slice() {
+                [] if !self.name_lookup.uses_remote_resolvers() => format!(
+                    "Address `{}`\n\nContract name lookup is not configured.{}",
+                    address, checksum_note
+                ),
+                [] => format!(
+                    "Address `{}`\n\nNo known contract name.{}",
+                    address, checksum_note
+                ),
+                [entry] => format!(
+                    "`{}`{}{}",
+                    entry.name,
+                    chain_note(entry.chain_id),
+                    checksum_note
+                ),
+                _ => format!(
+                    "Address `{}` has multiple names:\n\n{}{}",
+                    address,
+                    names
+                        .iter()
+                        .map(|entry| format!("- `{}`{}", entry.name, chain_note(entry.chain_id)))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    checksum_note
+                ),
+            },
+            Err(error) => {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Contract name lookup failed: {}", error),
+                    )
+                    .await;
+                format!(
+                    "Address `{}`\n\nContract name lookup failed.{}",
+                    address, checksum_note
+                )
+            }
+        }
+    }
+
+    /// Tells whether a prefetch of the document is scheduled or running.
+    fn prefetch_pending(&self, uri: &Url) -> bool {
+        self.prefetch_tasks
+            .get(&uri.to_string())
+            .map_or(false, |task| !task.is_finished())
+    }
+
+    /// Looks up the names of all selectors and addresses in the source that need a remote
+    /// query, with a single batch query per resolver, so that hovers and inlay hints find
+    /// them in the cache. Runs in the background once the text stopped changing and reports
+    /// progress if the client supports it. A pending prefetch of the same document is
+    /// cancelled.
+    fn prefetch(&self, uri: &Url, ast: &Block) {
+        if let Some((_, task)) = self.prefetch_tasks.remove(&uri.to_string()) {
+            task.abort();
+        }
+        let name_lookup = self.name_lookup.clone();
+        if !name_lookup.uses_remote_resolvers() {
+            return;
+        }
+        let classified_literals = classify_literals(ast);
+        let candidates = |literal_kind: LiteralKind| -> Vec<String> {
+            let mut keys: Vec<String> = classified_literals
+                .iter()
+                .filter(|(_, kind)| *kind == literal_kind)
+                .filter_map(|(literal, kind)| normalized_value(literal, kind))
+                .filter(|key| known_address(key).is_none())
+                .collect();
+            keys.sort();
+            keys.dedup();
+            keys
+        };
+        let selectors = candidates(LiteralKind::Selector);
+        let addresses = candidates(LiteralKind::Address);
+        if selectors.is_empty() && addresses.is_empty() {
+            return;
+        }
+
+        let client = self.client.clone();
+        let progress = self.work_done_progress.load(Ordering::Relaxed);
+        let task = tokio::spawn(async move {
+            tokio::time::sleep(PREFETCH_DELAY).await;
+            name_lookup.reload_cache().await;
+            let selectors: Vec<String> = selectors
+                .into_iter()
+                .filter(|selector| {
+                    name_lookup.cached_function_signatures(selector).is_none()
+                        && name_lookup.start_lookup(selector)
+                })
+                .collect();
+            let addresses: Vec<String> = addresses
+                .into_iter()
+                .filter(|address| {
+                    name_lookup.cached_contract_names(address).is_none()
+                        && name_lookup.start_lookup(address)
+                })
+                .collect();
+            if selectors.is_empty() && addresses.is_empty() {
+                return;
+            }
+            let mut guard = PrefetchGuard {
+                name_lookup: name_lookup.clone(),
+                keys: selectors.iter().chain(&addresses).cloned().collect(),
+                progress: None,
+            };
+
+            let token = NumberOrString::String(format!(
+                "yul-lsp/prefetch/{}",
+                PREFETCH_COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            if progress
+                && client
+                    .send_request::<request::WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                        token: token.clone(),
+                    })
+                    .await
+                    .is_ok()
+            {
+                guard.progress = Some((client.clone(), token.clone()));
+            }
+            let progress = guard.progress.is_some();
+            let report = |value: WorkDoneProgress| {
+                let client = &client;
+                let token = token.clone();
+                async move {
+                    if progress {
+                        client
+                            .send_notification::<notification::Progress>(ProgressParams {
+                                token,
+                                value: ProgressParamsValue::WorkDone(value),
+                            })
+                            .await;
+                    }
+                }
+            };
+
+            report(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+                title: "Looking up names".to_string(),
+                cancellable: Some(false),
+                message: Some(format!("{} selectors", selectors.len())),
+                percentage: Some(0),
+            }))
+            .await;
+            let selector_result = name_lookup.prefetch_function_signatures(&selectors).await;
+            report(WorkDoneProgress::Report(WorkDoneProgressReport {
+                cancellable: Some(false),
+                message: Some(format!("{} addresses", addresses.len())),
+                percentage: Some(50),
+            }))
+            .await;
+            let address_result = name_lookup.prefetch_contract_names(&addresses).await;
+            // Releases the keys and ends the progress.
+            drop(guard);
+
+            // Without any result, asking for the hints again would only schedule another
+            // prefetch of the same keys.
+            let resolved = (!selectors.is_empty() && selector_result.is_ok())
+                || (!addresses.is_empty() && address_result.is_ok());
+            for error in [selector_result.err(), address_result.err()]
+                .into_iter()
+                .flatten()
+            {
+                client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Name prefetch failed: {}", error),
+                    )
+                    .await;
+            }
+            if resolved {
+                refresh_inlay_hints(&client).await;
+            }
+        });
+        self.prefetch_tasks.insert(uri.to_string(), task);
+    }
+
+    /// Reads files on a thread where blocking is allowed, so that large build directories do
+    /// not stall the server.
+    async fn load_blocking(
+        &self,
+        load: impl FnOnce(NameLookup) -> Vec<(PathBuf, eyre::Report)> + Send + 'static,
+    ) -> Vec<(PathBuf, eyre::Report)> {
+        let name_lookup = self.name_lookup.clone();
+        match tokio::task::spawn_blocking(move || load(name_lookup)).await {
+            Ok(errors) => errors,
+            Err(error) => vec![(PathBuf::new(), eyre::eyre!(error))],
+        }
+    }
+
+    async fn log_load_errors(&self, description: &str, errors: Vec<(PathBuf, eyre::Report)>) {
+        for (path, error) in errors {
+            self.client
+                .log_message(
+                    MessageType::ERROR,
+                    format!(
+                        "Failed to load {} from {}: {}",
+                        description,
+                        path.display(),
+                        error
+                    ),
+                )
+                .await;
+        }
+    }
+
     async fn on_change(&self, params: TextDocumentItem) {
         let rope = ropey::Rope::from_str(&params.text);
+        if let Some(ast) = self
+            .publish_findings(&params.uri, &params.text, Some(params.version))
+            .await
+        {
+            self.prefetch(&params.uri, &ast);
+        }
         self.document_map.insert(params.uri.to_string(), rope);
     }
+
+    /// Checks all open documents again, e.g. after the known signatures changed.
+    async fn analyze_open_documents(&self) {
+        let documents: Vec<(String, String)> = self
+            .document_map
+            .iter()
+            .map(|entry| (entry.key().clone(), entry.value().to_string()))
+            .collect();
+        for (uri, text) in documents {
+            if let Ok(uri) = Url::parse(&uri) {
+                self.publish_findings(&uri, &text, None).await;
+            }
+        }
+    }
+
+    /// Runs the analysis passes and publishes their findings. Returns the resolved AST if the
+    /// text parses.
+    async fn publish_findings(&self, uri: &Url, text: &str, version: Option<i32>) -> Option<Block> {
+        let rope = ropey::Rope::from_str(text);
+        match parse_block(text) {
+            Ok(mut ast) => {
+                resolve::<EVMDialect>(&mut ast);
+                // The lookups are not kept across the await below.
+                let findings = {
+                    let known_signatures: SignatureLookup =
+                        &|selector: &str| self.name_lookup.local_function_signatures(selector);
+                    let known_events: EventLookup =
+                        &|topic: &str| self.name_lookup.local_event_signatures(topic);
+                    analyze(text, &ast, Some(known_signatures), Some(known_events))
+                };
+                let diagnostics = findings
+                    .iter()
+                    .filter_map(|finding| finding_to_diagnostic(uri, &rope, finding))
+                    .collect();
+                self.finding_map.insert(uri.to_string(), findings);
+                self.client
+                    .publish_diagnostics(uri.clone(), diagnostics, version)
+                    .await;
+                Some(ast)
+            }
+            Err(_) => {
+                // The offsets of earlier findings do not match the new text.
+                self.finding_map.remove(&uri.to_string());
+                self.client
+                    .publish_diagnostics(uri.clone(), vec![], version)
+                    .await;
+                None
+            }
+        }
+    }
+}
+
+/// Explains the errors raised by `require` and by failed checks in Solidity.
+fn standard_error_note(signature: &str) -> &'static str {
+    match signature {
+        "Error(string)" => "\n\nRaised by `require` and `revert` with a reason string.",
+        "Panic(uint256)" => {
+            "\n\nRaised by failed assertions, arithmetic overflow, division by zero and out-of-bounds accesses."
+        }
+        _ => "",
+    }
+}
+
+/// Joins the distinct names of an address.
+fn contract_names_label(entries: &[AddressEntry]) -> String {
+    let mut names: Vec<&str> = vec![];
+    for entry in entries {
+        if !names.contains(&entry.name.as_str()) {
+            names.push(&entry.name);
+        }
+    }
+    names.join(" | ")
+}
+
+fn chain_note(chain_id: Option<u64>) -> String {
+    match chain_id {
+        Some(chain_id) => format!(" (chain {})", chain_id),
+        None => "".to_string(),
+    }
+}
+
+/// Asks the editor to request inlay hints again, e.g. after names have been resolved.
+async fn refresh_inlay_hints(client: &Client) {
+    let refresh = client
+        .send_request::<request::InlayHintRefreshRequest>(())
+        .await;
+    if let Err(error) = refresh {
+        client
+            .log_message(
+                MessageType::WARNING,
+                format!("Inlay hint refresh failed: {}", error),
+            )
+            .await;
+    }
+}
+
+fn position_to_offset(rope: &Rope, position: Position) -> Option<usize> {
+    let line_start = rope.try_line_to_char(position.line as usize).ok()?;
+    rope.try_char_to_byte(line_start + position.character as usize)
+        .ok()
+}
+
+fn offset_to_position(rope: &Rope, offset: usize) -> Option<Position> {
+    let line = rope.try_byte_to_line(offset).ok()?;
+    let line_start = rope.try_line_to_char(line).ok()?;
+    let char_index = rope.try_byte_to_char(offset).ok()?;
+    Some(Position::new(line as u32, (char_index - line_start) as u32))
+}
+
+fn location_to_range(rope: &Rope, location: &SourceLocation) -> Option<Range> {
+    Some(Range::new(
+        offset_to_position(rope, location.start)?,
+        offset_to_position(rope, location.end)?,
+    ))
+}
+
+fn finding_to_diagnostic(uri: &Url, rope: &Rope, finding: &Finding) -> Option<Diagnostic> {
+    let related_information = finding
+        .related
+        .iter()
+        .filter_map(|related| {
+            Some(DiagnosticRelatedInformation {
+                location: Location::new(uri.clone(), location_to_range(rope, &related.location)?),
+                message: related.message.clone(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Some(Diagnostic {
+        range: location_to_range(rope, &finding.location)?,
+        severity: Some(match finding.severity {
+            Severity::Error => DiagnosticSeverity::ERROR,
+            Severity::Warning => DiagnosticSeverity::WARNING,
+        }),
+        source: Some("yul-lsp".to_string()),
+        message: finding.message.clone(),
+        related_information: if related_information.is_empty() {
+            None
+        } else {
+            Some(related_information)
+        },
+        tags: if finding.unnecessary {
+            Some(vec![DiagnosticTag::UNNECESSARY])
+        } else {
+            None
+        },
+        ..Diagnostic::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dune_apis::{DEFAULT_CONTRACT_NAME_QUERY_ID, DEFAULT_FUNCTION_SIGNATURE_QUERY_ID};
+    use crate::dune_mock::{MockDune, MockExecution};
+    use crate::resolver::ResolverKind;
+    use indoc::indoc;
+    use serde_json::json;
+    use tower_lsp::LspService;
+
+    async fn hover_text(backend: &Backend, uri: &Url, source: &str, needle: &str) -> String {
+        let rope = Rope::from_str(source);
+        let offset = source.find(needle).unwrap() + 2;
+        let hover = backend
+            .hover(HoverParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: offset_to_position(&rope, offset).unwrap(),
+                },
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+        match hover.contents {
+            HoverContents::Markup(markup) => markup.value,
+            contents => panic!("Unexpected hover contents {:?}", contents),
+        }
+    }
+
+    #[tokio::test]
+    async fn hover_with_dune() {
+        let mock_dune = MockDune::start().await;
+        mock_dune.respond(
+            DEFAULT_FUNCTION_SIGNATURE_QUERY_ID,
+            "0x70a08231",
+            MockExecution::rows(vec![json!({"signature": "balanceOf(address)"})]),
+        );
+        mock_dune.respond(
+            DEFAULT_CONTRACT_NAME_QUERY_ID,
+            "0xe592427a0aece92de3edee1f18e0157c05861564",
+            MockExecution::rows(vec![json!({"name": "SwapRouter"})]),
+        );
+
+        let (service, _) = LspService::build(|client| Backend {
+            client,
+            name_lookup: NameLookup::new(),
+            document_map: DashMap::new(),
+            finding_map: DashMap::new(),
+            work_done_progress: AtomicBool::new(false),
+            storage_namespaces: RwLock::new(vec![]),
+            prefetch_tasks: DashMap::new(),
+        })
+        .finish();
+        let backend = service.inner();
+        backend
+            .name_lookup
+            .set_resolvers(&[ResolverKind::Dune], None, mock_dune.config());
+
+        let source = indoc! {"
+            {
+                switch shr(224, calldataload(0))
+                case 0x70a08231 {
+                    let router := 0xe592427a0aece92de3edee1f18e0157c05861564
+                    let success := staticcall(gas(), 0x1, 0, 0x80, 0, 0x20)
+                    let implementation := sload(0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc)
+                    let adminSlot := 0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103
+                    let admin := sload(adminSlot)
+                    let word := 0x1111111111111111111111111111111111111111111111111111111111111111
+                }
+                case 0x12345678 {}
+            }
+        "};
+        let uri = Url::parse("file:///test.yul").unwrap();
+        backend
+            .document_map
+            .insert(uri.to_string(), Rope::from_str(source));
+
+        assert_eq!(
+            hover_text(backend, &uri, source, "0x70a08231").await,
+            "`balanceOf(address)`"
+        );
+        assert_eq!(
+            hover_text(backend, &uri, source, "0xe592427a").await,
+            "`SwapRouter`"
+        );
+        assert_eq!(
+            hover_text(backend, &uri, source, "0x12345678").await,
+            "Function selector `0x12345678`\n\nNo known signature."
+        );
+
+        // Precompiles are described without a lookup.
+        assert!(hover_text(backend, &uri, source, "0x1,")
+            .await
+            .starts_with("Precompile `ecrecover` at `0x01`\n\n- Input: 128 bytes"));
+        assert!(hover_text(backend, &uri, source, "0x360894a1")
+            .await
+            .starts_with("EIP-1967 implementation slot"));
+        assert!(hover_text(backend, &uri, source, "0xb5312768")
+            .await
+            .starts_with("EIP-1967 admin slot"));
+        assert!(hover_text(backend, &uri, source, "0x11111111")
+            .await
+            .starts_with("Number `0x1111"));
+
+        // Served from the cache the second time.
+        hover_text(backend, &uri, source, "0x70a08231").await;
+        assert_eq!(mock_dune.executed().len(), 3);
+    }
 }
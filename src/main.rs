@@ -1,7 +1,30 @@
+pub mod abi;
+pub mod abi_artifacts;
+pub mod address;
+pub mod address_book;
+pub mod config;
+pub mod control_flow;
 pub mod definition_finder;
+pub mod diagnostics;
+pub mod dispatcher;
 pub mod dune_apis;
+#[cfg(test)]
+pub mod dune_mock;
+pub mod event_checker;
 pub mod identifier_finder;
+pub mod known_addresses;
+pub mod literal_checker;
 pub mod literal_finder;
+pub mod literal_hover;
+pub mod literal_value;
+pub mod name_cache;
+pub mod name_lookup;
+pub mod resolver;
+pub mod scope_checker;
+pub mod signature_db;
+pub mod storage_slots;
+pub mod unreachable_checker;
+pub mod unused_checker;
 
 mod lsp_server;
 
@@ -13,10 +36,13 @@ use yultsur::yul::IdentifierID;
 use yultsur::yul_parser::parse_block;
 
 use crate::lsp_server::Backend;
+use crate::name_lookup::NameLookup;
 use dashmap::DashMap;
 use tower_lsp::{LspService, Server};
 
 use std::fs::read_to_string;
+use std::sync::atomic::AtomicBool;
+use std::sync::RwLock;
 
 #[tokio::main]
 async fn main() {
@@ -27,7 +53,12 @@ async fn main() {
 
     let (service, socket) = LspService::build(|client| Backend {
         client,
+        name_lookup: NameLookup::new(),
         document_map: DashMap::new(),
+        finding_map: DashMap::new(),
+        work_done_progress: AtomicBool::new(false),
+        storage_namespaces: RwLock::new(vec![]),
+        prefetch_tasks: DashMap::new(),
     })
     .finish();
     Server::new(stdin, stdout, socket).serve(service).await;
@@ -0,0 +1,432 @@
+use crate::address_book::AddressEntry;
+use eyre::Result;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs::{create_dir_all, metadata, read_to_string, rename, write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long names found remotely are kept, in seconds.
+const POSITIVE_TTL: u64 = 30 * 24 * 60 * 60;
+/// How long it is remembered that nothing was found remotely. Shorter, since names may be
+/// added to the remote source later.
+const NEGATIVE_TTL: u64 = 24 * 60 * 60;
+/// Minimum time between two checks of the file for changes made by other servers.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheEntry<T> {
+    values: Vec<T>,
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+}
+
+impl<T> CacheEntry<T> {
+    fn is_expired(&self, now: u64) -> bool {
+        let ttl = if self.values.is_empty() {
+            NEGATIVE_TTL
+        } else {
+            POSITIVE_TTL
+        };
+        self.fetched_at + ttl < now
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct CacheFile {
+    signatures: HashMap<String, CacheEntry<String>>,
+    contracts: HashMap<String, CacheEntry<AddressEntry>>,
+    /// When the cache was last cleared, in seconds since the Unix epoch. Entries fetched
+    /// until then are dropped, also when another server still has them in memory.
+    cleared_at: u64,
+}
+
+impl CacheFile {
+    /// Adds the entries of the other file that are newer than the own ones.
+    fn merge(&mut self, other: CacheFile) {
+        fn merge_table<T>(
+            table: &mut HashMap<String, CacheEntry<T>>,
+            other: HashMap<String, CacheEntry<T>>,
+        ) {
+            for (key, entry) in other {
+                match table.get(&key) {
+                    Some(existing) if existing.fetched_at >= entry.fetched_at => {}
+                    _ => {
+                        table.insert(key, entry);
+                    }
+                }
+            }
+        }
+        merge_table(&mut self.signatures, other.signatures);
+        merge_table(&mut self.contracts, other.contracts);
+        self.cleared_at = self.cleared_at.max(other.cleared_at);
+        let cleared_at = self.cleared_at;
+        self.retain(|fetched_at, _| fetched_at > cleared_at);
+    }
+
+    fn remove_expired(&mut self, now: u64) {
+        self.retain(|fetched_at, negative| {
+            let ttl = if negative { NEGATIVE_TTL } else { POSITIVE_TTL };
+            fetched_at + ttl >= now
+        });
+    }
+
+    /// Keeps the entries for which the predicate, given the fetch time and whether the entry
+    /// is negative, returns true.
+    fn retain(&mut self, keep: impl Fn(u64, bool) -> bool) {
+        self.signatures
+            .retain(|_, entry| keep(entry.fetched_at, entry.values.is_empty()));
+        self.contracts
+            .retain(|_, entry| keep(entry.fetched_at, entry.values.is_empty()));
+    }
+}
+
+/// Values that can be stored in the cache.
+pub trait Cacheable: Clone + Serialize + DeserializeOwned {
+    fn table(file: &CacheFile) -> &HashMap<String, CacheEntry<Self>>;
+    fn table_mut(file: &mut CacheFile) -> &mut HashMap<String, CacheEntry<Self>>;
+}
+
+impl Cacheable for String {
+    fn table(file: &CacheFile) -> &HashMap<String, CacheEntry<String>> {
+        &file.signatures
+    }
+
+    fn table_mut(file: &mut CacheFile) -> &mut HashMap<String, CacheEntry<String>> {
+        &mut file.signatures
+    }
+}
+
+impl Cacheable for AddressEntry {
+    fn table(file: &CacheFile) -> &HashMap<String, CacheEntry<AddressEntry>> {
+        &file.contracts
+    }
+
+    fn table_mut(file: &mut CacheFile) -> &mut HashMap<String, CacheEntry<AddressEntry>> {
+        &mut file.contracts
+    }
+}
+
+#[derive(Default, Debug)]
+struct CacheState {
+    path: Option<PathBuf>,
+    file: CacheFile,
+    /// Modification time of the file when it was last read or written.
+    modified: Option<SystemTime>,
+    /// When the file was last checked for changes.
+    checked_at: Option<Instant>,
+    /// Whether there are changes that are not saved yet.
+    dirty: bool,
+}
+
+/// Cache of remote lookup results, including empty ones, with expiry.
+///
+/// If a path is set, the cache is stored in a JSON file, which makes it survive restarts and
+/// shares it between servers running in different editor windows. Changes made by other
+/// servers are picked up by `reload` when the file's modification time changes.
+///
+/// Lookups and inserts only use the cache in memory. `reload` and `save` block on file I/O
+/// and should not run on the async runtime.
+#[derive(Default, Debug)]
+pub struct NameCache {
+    state: Mutex<CacheState>,
+    /// Serializes writes of the file. Lookups do not wait for them.
+    save_lock: Mutex<()>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    metadata(path).ok()?.modified().ok()
+}
+
+fn read_cache_file(path: &Path) -> Option<CacheFile> {
+    serde_json::from_str(&read_to_string(path).ok()?).ok()
+}
+
+fn write_cache_file(path: &Path, file: &CacheFile) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        create_dir_all(directory)?;
+    }
+    // Writing to a temporary file first so that readers never see a partial file.
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    write(&temporary_path, serde_json::to_string(file)?)?;
+    rename(&temporary_path, path)?;
+    Ok(())
+}
+
+/// Returns `$XDG_CACHE_HOME/yul-lsp/names.json`, falling back to `~/.cache`.
+pub fn default_cache_path() -> Option<PathBuf> {
+    let cache_home = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|cache_home| !cache_home.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".cache")))?;
+    Some(cache_home.join("yul-lsp").join("names.json"))
+}
+
+impl NameCache {
+    pub fn new() -> NameCache {
+        NameCache::default()
+    }
+
+    /// Starts storing the cache in the file, loading the entries it already contains.
+    /// `None` keeps the cache in memory only.
+    pub fn set_path(&self, path: Option<PathBuf>) {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.path = path;
+            state.modified = None;
+            state.checked_at = None;
+        }
+        self.reload();
+    }
+
+    /// Picks up changes written by other servers. The file is checked at most once per
+    /// `RELOAD_INTERVAL`, so this can be called before every batch of lookups.
+    pub fn reload(&self) {
+        let (path, known_modified) = {
+            let mut state = self.state.lock().unwrap();
+            let recently_checked = state
+                .checked_at
+                .map_or(false, |checked_at| checked_at.elapsed() < RELOAD_INTERVAL);
+            match state.path.clone() {
+                Some(path) if !recently_checked => {
+                    state.checked_at = Some(Instant::now());
+                    (path, state.modified)
+                }
+                _ => return,
+            }
+        };
+        let modified = modified_time(&path);
+        if modified.is_none() || modified == known_modified {
+            return;
+        }
+
+        // Reading without holding the lock, so that lookups are not held up meanwhile.
+        let file = read_cache_file(&path);
+        let mut state = self.state.lock().unwrap();
+        if state.path.as_ref() != Some(&path) {
+            return;
+        }
+        if let Some(file) = file {
+            state.file.merge(file);
+        }
+        state.modified = modified;
+    }
+
+    /// Returns the cached values, which are empty if the key is known to be unknown. Returns
+    /// `None` if the key is not cached or the entry expired.
+    pub fn get<T: Cacheable>(&self, key: &str) -> Option<Vec<T>> {
+        self.get_at(key, now())
+    }
+
+    fn get_at<T: Cacheable>(&self, key: &str, now: u64) -> Option<Vec<T>> {
+        let state = self.state.lock().unwrap();
+        T::table(&state.file)
+            .get(&key.to_lowercase())
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.values.clone())
+    }
+
+    pub fn insert<T: Cacheable>(&self, key: &str, values: Vec<T>) {
+        self.insert_at(key, values, now())
+    }
+
+    fn insert_at<T: Cacheable>(&self, key: &str, values: Vec<T>, now: u64) {
+        self.insert_many_at(vec![(key.to_string(), values)], now)
+    }
+
+    /// Inserts the results of a batch at once.
+    pub fn insert_many<T: Cacheable>(&self, entries: Vec<(String, Vec<T>)>) {
+        self.insert_many_at(entries, now())
+    }
+
+    fn insert_many_at<T: Cacheable>(&self, entries: Vec<(String, Vec<T>)>, now: u64) {
+        let mut state = self.state.lock().unwrap();
+        let table = T::table_mut(&mut state.file);
+        for (key, values) in entries {
+            table.insert(
+                key.to_lowercase(),
+                CacheEntry {
+                    values,
+                    fetched_at: now,
+                },
+            );
+        }
+        state.dirty = true;
+    }
+
+    /// Writes the changes since the last save to the file, together with the entries other
+    /// servers wrote in the meantime. Expired entries are dropped.
+    pub fn save(&self) -> Result<()> {
+        let _save_guard = self.save_lock.lock().unwrap();
+        let (path, mut file) = {
+            let mut state = self.state.lock().unwrap();
+            match &state.path {
+                Some(path) if state.dirty => {
+                    state.dirty = false;
+                    (path.clone(), state.file.clone())
+                }
+                _ => return Ok(()),
+            }
+        };
+
+        // Keep entries written by other servers since the last read.
+        if let Some(other) = read_cache_file(&path) {
+            file.merge(other);
+        }
+        file.remove_expired(now());
+        let written = write_cache_file(&path, &file);
+
+        let mut state = self.state.lock().unwrap();
+        if written.is_err() {
+            state.dirty = true;
+        } else if state.path.as_ref() == Some(&path) {
+            state.modified = modified_time(&path);
+        }
+        state.file.merge(file);
+        state.file.remove_expired(now());
+        written
+    }
+
+    /// Removes all entries. Other servers sharing the file drop their entries as well when
+    /// they see it, so that they do not write them back.
+    pub fn clear(&self) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.file = CacheFile {
+                cleared_at: now(),
+                ..CacheFile::default()
+            };
+            state.dirty = true;
+        }
+        self.save()
+    }
+
+    /// Describes the cache contents. If a key is given, its entries are included.
+    pub fn inspect(&self, key: Option<&str>) -> Value {
+        self.reload();
+        let state = self.state.lock().unwrap();
+        let now = now();
+
+        fn table_summary<T>(table: &HashMap<String, CacheEntry<T>>, now: u64) -> Value {
+            let live: Vec<_> = table
+                .values()
+                .filter(|entry| !entry.is_expired(now))
+                .collect();
+            json!({
+                "entries": live.len(),
+                "negative": live.iter().filter(|entry| entry.values.is_empty()).count(),
+                "expired": table.len() - live.len(),
+            })
+        }
+
+        let mut summary = json!({
+            "path": state.path,
+            "signatures": table_summary(&state.file.signatures, now),
+            "contracts": table_summary(&state.file.contracts, now),
+        });
+        if let Some(key) = key {
+            let key = key.to_lowercase();
+            summary["entry"] = json!({
+                "signatures": state.file.signatures.get(&key),
+                "contracts": state.file.contracts.get(&key),
+            });
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temporary_cache_path(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("yul-lsp-test-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        directory.join("names.json")
+    }
+
+    #[test]
+    fn expiry() {
+        let cache = NameCache::new();
+        cache.insert_at("0x12345678", vec!["foo()".to_string()], 1000);
+        cache.insert_at::<String>("0x87654321", vec![], 1000);
+
+        assert_eq!(
+            cache.get_at::<String>("0x12345678", 1000 + NEGATIVE_TTL + 1),
+            Some(vec!["foo()".to_string()])
+        );
+        assert_eq!(cache.get_at::<String>("0x87654321", 1000), Some(vec![]));
+        assert_eq!(
+            cache.get_at::<String>("0x87654321", 1000 + NEGATIVE_TTL + 1),
+            None
+        );
+        assert_eq!(
+            cache.get_at::<String>("0x12345678", 1000 + POSITIVE_TTL + 1),
+            None
+        );
+        // Tables are separate.
+        assert_eq!(cache.get_at::<AddressEntry>("0x12345678", 1000), None);
+    }
+
+    #[test]
+    fn shared_file() {
+        let path = temporary_cache_path("shared");
+        let first = NameCache::new();
+        first.set_path(Some(path.clone()));
+        let second = NameCache::new();
+        second.set_path(Some(path.clone()));
+
+        let now = now();
+        first.insert_at("0x12345678", vec!["foo()".to_string()], now - 10);
+        // Expired entries are not written.
+        first.insert_at("0x87654321", vec!["bar()".to_string()], 1000);
+        first.save().unwrap();
+        second.insert(
+            "0xe592427a0aece92de3edee1f18e0157c05861564",
+            vec![AddressEntry {
+                name: "SwapRouter".to_string(),
+                chain_id: None,
+            }],
+        );
+        second.save().unwrap();
+
+        // A new server sees both entries.
+        let restarted = NameCache::new();
+        restarted.set_path(Some(path.clone()));
+        assert_eq!(
+            restarted.get::<String>("0x12345678"),
+            Some(vec!["foo()".to_string()])
+        );
+        assert_eq!(restarted.inspect(None)["contracts"]["entries"], json!(1));
+        assert_eq!(restarted.inspect(None)["signatures"]["expired"], json!(0));
+
+        restarted.clear().unwrap();
+        assert_eq!(restarted.get::<String>("0x12345678"), None);
+
+        // Saving the other server's changes does not bring back the cleared entries.
+        first.insert_at(
+            "0xa9059cbb",
+            vec!["transfer(address,uint256)".to_string()],
+            now + 10,
+        );
+        first.save().unwrap();
+        let restarted = NameCache::new();
+        restarted.set_path(Some(path.clone()));
+        assert_eq!(restarted.get::<String>("0x12345678"), None);
+        assert_eq!(
+            restarted.get::<String>("0xa9059cbb"),
+            Some(vec!["transfer(address,uint256)".to_string()])
+        );
+    }
+}
@@ -0,0 +1,538 @@
+use crate::abi_artifacts::{ArtifactIndex, EventSignature};
+use crate::address_book::{AddressBook, AddressEntry};
+use crate::dune_apis::{DuneClient, DuneConfig};
+use crate::name_cache::{Cacheable, NameCache};
+use crate::resolver::{
+    AddressBookResolver, ArtifactResolver, DuneResolver, Resolver, ResolverKind,
+    SignatureDatabaseResolver,
+};
+use crate::signature_db::SignatureDatabase;
+use dashmap::DashMap;
+use eyre::Result;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+/// Runs one kind of lookup on a resolver.
+type Lookup<T> = for<'a> fn(&'a dyn Resolver, &'a str) -> BoxFuture<'a, Result<Vec<T>>>;
+
+fn function_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    selector: &'a str,
+) -> BoxFuture<'a, Result<Vec<String>>> {
+    resolver.function_signatures(selector)
+}
+
+fn error_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    selector: &'a str,
+) -> BoxFuture<'a, Result<Vec<String>>> {
+    resolver.error_signatures(selector)
+}
+
+fn event_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    topic: &'a str,
+) -> BoxFuture<'a, Result<Vec<EventSignature>>> {
+    resolver.event_signatures(topic)
+}
+
+fn contract_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    address: &'a str,
+) -> BoxFuture<'a, Result<Vec<AddressEntry>>> {
+    resolver.contract_names(address)
+}
+
+/// Runs one kind of lookup for several keys at once on a resolver.
+type BatchLookup<T> =
+    for<'a> fn(&'a dyn Resolver, &'a [String]) -> BoxFuture<'a, Result<HashMap<String, Vec<T>>>>;
+
+fn function_batch_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    selectors: &'a [String],
+) -> BoxFuture<'a, Result<HashMap<String, Vec<String>>>> {
+    resolver.function_signatures_batch(selectors)
+}
+
+fn contract_batch_lookup<'a>(
+    resolver: &'a dyn Resolver,
+    addresses: &'a [String],
+) -> BoxFuture<'a, Result<HashMap<String, Vec<AddressEntry>>>> {
+    resolver.contract_names_batch(addresses)
+}
+
+/// Resolves selectors to function signatures and addresses to contract names by asking a
+/// chain of resolvers in the configured order. The first non-empty result wins.
+///
+/// Results of remote resolvers are cached, so that repeated requests do not trigger new
+/// remote queries. The cache can be persisted, see `NameCache`.
+///
+/// Cloning is cheap and clones share the cache, which allows lookups to run in background
+/// tasks.
+#[derive(Clone, Debug)]
+pub struct NameLookup {
+    http_client: reqwest::Client,
+    resolvers: Arc<RwLock<Vec<Arc<dyn Resolver>>>>,
+    signature_database: Arc<RwLock<SignatureDatabase>>,
+    artifacts: Arc<RwLock<ArtifactIndex>>,
+    artifact_paths: Arc<RwLock<Vec<PathBuf>>>,
+    address_book: Arc<RwLock<AddressBook>>,
+    address_book_paths: Arc<RwLock<Vec<PathBuf>>>,
+    cache: Arc<NameCache>,
+    // Keys of lookups currently being executed in the background.
+    in_flight: Arc<DashMap<String, ()>>,
+}
+
+impl NameLookup {
+    pub fn new() -> NameLookup {
+        let name_lookup = NameLookup {
+            http_client: reqwest::Client::new(),
+            resolvers: Arc::new(RwLock::new(vec![])),
+            signature_database: Arc::new(RwLock::new(SignatureDatabase::bundled())),
+            artifacts: Arc::new(RwLock::new(ArtifactIndex::new())),
+            artifact_paths: Arc::new(RwLock::new(vec![])),
+            address_book: Arc::new(RwLock::new(AddressBook::new())),
+            address_book_paths: Arc::new(RwLock::new(vec![])),
+            cache: Arc::new(NameCache::new()),
+            in_flight: Arc::new(DashMap::new()),
+        };
+        name_lookup.set_resolvers(&ResolverKind::default_order(), None, DuneConfig::default());
+        name_lookup
+    }
+
+    /// Replaces the resolver chain. Names from the address book are limited to the given
+    /// chain, if any.
+    pub fn set_resolvers(
+        &self,
+        kinds: &[ResolverKind],
+        chain_id: Option<u64>,
+        dune_config: DuneConfig,
+    ) {
+        let resolvers = kinds
+            .iter()
+            .map(|kind| -> Arc<dyn Resolver> {
+                match kind {
+                    ResolverKind::Artifacts => Arc::new(ArtifactResolver {
+                        artifacts: self.artifacts.clone(),
+                    }),
+                    ResolverKind::AddressBook => Arc::new(AddressBookResolver {
+                        address_book: self.address_book.clone(),
+                        chain_id,
+                    }),
+                    ResolverKind::SignatureDatabase => Arc::new(SignatureDatabaseResolver {
+                        database: self.signature_database.clone(),
+                    }),
+                    ResolverKind::Dune => Arc::new(DuneResolver {
+                        dune_client: DuneClient::new(self.http_client.clone(), dune_config.clone()),
+                    }),
+                }
+            })
+            .collect();
+        *self.resolvers.write().unwrap() = resolvers;
+    }
+
+    /// Adds a resolver to the end of the chain.
+    #[cfg(test)]
+    pub fn add_resolver(&self, resolver: Arc<dyn Resolver>) {
+        self.resolvers.write().unwrap().push(resolver);
+    }
+
+    /// Returns the resolvers that can answer lookups, in order.
+    fn resolvers(&self) -> Vec<Arc<dyn Resolver>> {
+        self.resolvers
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|resolver| resolver.is_available())
+            .cloned()
+            .collect()
+    }
+
+    /// Persists the cache of remote results in the file. `None` keeps it in memory only.
+    pub fn set_cache_path(&self, path: Option<PathBuf>) {
+        self.cache.set_path(path);
+    }
+
+    pub fn clear_cache(&self) -> Result<()> {
+        self.cache.clear()
+    }
+
+    pub fn inspect_cache(&self, key: Option<&str>) -> Value {
+        self.cache.inspect(key)
+    }
+
+    /// Adds user-supplied signature files to the local database. Returns errors for files
+    /// that could not be loaded.
+    pub fn load_signature_files(&self, paths: &[PathBuf]) -> Vec<(PathBuf, eyre::Report)> {
+        let mut signature_database = self.signature_database.write().unwrap();
+        paths
+            .iter()
+            .filter_map(|path| match signature_database.load_file(path) {
+                Ok(_) => None,
+                Err(error) => Some((path.clone(), error)),
+            })
+            .collect()
+    }
+
+    /// Loads signatures from ABI artifacts in the given files and directories, replacing any
+    /// previously loaded ones. Returns errors for files that could not be loaded.
+    pub fn load_artifacts(&self, paths: &[PathBuf]) -> Vec<(PathBuf, eyre::Report)> {
+        *self.artifact_paths.write().unwrap() = paths.to_vec();
+        self.reload_artifacts()
+    }
+
+    /// Reads the artifacts again, e.g. after the project was rebuilt.
+    pub fn reload_artifacts(&self) -> Vec<(PathBuf, eyre::Report)> {
+        let mut artifacts = ArtifactIndex::new();
+        let errors = self
+            .artifact_paths
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|path| artifacts.load_path(path))
+            .collect();
+        *self.artifacts.write().unwrap() = artifacts;
+        errors
+    }
+
+    pub fn artifact_paths(&self) -> Vec<PathBuf> {
+        self.artifact_paths.read().unwrap().clone()
+    }
+
+    /// Loads the address book from the given deployment files and directories, replacing any
+    /// previously loaded entries.
+    pub fn load_address_book(&self, paths: &[PathBuf]) -> Vec<(PathBuf, eyre::Report)> {
+        *self.address_book_paths.write().unwrap() = paths.to_vec();
+        self.reload_address_book()
+    }
+
+    /// Reads the address book again, e.g. after new deployments.
+    pub fn reload_address_book(&self) -> Vec<(PathBuf, eyre::Report)> {
+        let mut address_book = AddressBook::new();
+        let errors = self
+            .address_book_paths
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|path| address_book.load_path(path))
+            .collect();
+        *self.address_book.write().unwrap() = address_book;
+        errors
+    }
+
+    pub fn address_book_paths(&self) -> Vec<PathBuf> {
+        self.address_book_paths.read().unwrap().clone()
+    }
+
+    /// Tells whether any resolver in the chain may query the network.
+    pub fn uses_remote_resolvers(&self) -> bool {
+        self.resolvers().iter().any(|resolver| resolver.is_remote())
+    }
+
+    /// Returns the first non-empty result of the local resolvers. Never blocks on the network.
+    fn local_lookup<T>(&self, key: &str, lookup: Lookup<T>) -> Vec<T> {
+        for resolver in self.resolvers() {
+            if resolver.is_remote() {
+                continue;
+            }
+            match lookup(resolver.as_ref(), key).now_or_never() {
+                Some(Ok(values)) if !values.is_empty() => return values,
+                _ => continue,
+            }
+        }
+        vec![]
+    }
+
+    /// Returns the result if it can be determined without a remote query, i.e. if a local
+    /// resolver preceding all remote ones knows the key or the remote result is cached.
+    fn cached_lookup<T: Cacheable>(&self, key: &str, lookup: Lookup<T>) -> Option<Vec<T>> {
+        let mut remote_result_empty = false;
+        for resolver in self.resolvers() {
+            if resolver.is_remote() {
+                if remote_result_empty {
+                    continue;
+                }
+                match self.cache.get::<T>(key) {
+                    Some(values) if !values.is_empty() => return Some(values),
+                    Some(_) => remote_result_empty = true,
+                    None => return None,
+                }
+                continue;
+            }
+            match lookup(resolver.as_ref(), key).now_or_never() {
+                Some(Ok(values)) if !values.is_empty() => return Some(values),
+                _ => continue,
+            }
+        }
+        Some(vec![])
+    }
+
+    async fn lookup<T: Cacheable>(&self, key: &str, lookup: Lookup<T>) -> Result<Vec<T>> {
+        self.reload_cache().await;
+        if let Some(values) = self.cached_lookup(key, lookup) {
+            return Ok(values);
+        }
+
+        // Failed lookups are not cached, so that they are retried.
+        let mut error = None;
+        for resolver in self.resolvers() {
+            match lookup(resolver.as_ref(), key).await {
+                Ok(values) => {
+                    if resolver.is_remote() {
+                        self.cache.insert(key, values.clone());
+                        self.save_cache().await;
+                    }
+                    if !values.is_empty() {
+                        return Ok(values);
+                    }
+                }
+                Err(lookup_error) => error = Some(lookup_error),
+            }
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Picks up names cached by other servers, on a thread where blocking is allowed. Cached
+    /// lookups only see them after this.
+    pub async fn reload_cache(&self) {
+        let cache = self.cache.clone();
+        let _ = tokio::task::spawn_blocking(move || cache.reload()).await;
+    }
+
+    /// Writes the cache file on a thread where blocking is allowed. The cache only saves
+    /// queries, so failing to persist it is harmless.
+    async fn save_cache(&self) {
+        let cache = self.cache.clone();
+        let _ = tokio::task::spawn_blocking(move || cache.save()).await;
+    }
+
+    /// Fills the cache for all keys that need a remote query, with one batch query per remote
+    /// resolver. Keys that remain unknown are passed on to the next remote resolver. Returns
+    /// the error of the last failing batch, if any.
+    async fn prefetch<T: Cacheable>(
+        &self,
+        keys: &[String],
+        lookup: Lookup<T>,
+        batch_lookup: BatchLookup<T>,
+    ) -> Result<()> {
+        self.reload_cache().await;
+        let mut pending: Vec<String> = keys
+            .iter()
+            .map(|key| key.to_lowercase())
+            .filter(|key| self.cached_lookup(key, lookup).is_none())
+            .collect();
+        pending.sort();
+        pending.dedup();
+
+        let mut error = None;
+        for resolver in self.resolvers() {
+            if pending.is_empty() {
+                break;
+            }
+            if !resolver.is_remote() {
+                continue;
+            }
+            match batch_lookup(resolver.as_ref(), &pending).await {
+                Ok(results) => {
+                    pending.retain(|key| results.get(key).map_or(true, Vec::is_empty));
+                    self.cache.insert_many(results.into_iter().collect());
+                    self.save_cache().await;
+                }
+                Err(batch_error) => error = Some(batch_error),
+            }
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn prefetch_function_signatures(&self, selectors: &[String]) -> Result<()> {
+        self.prefetch(selectors, function_lookup, function_batch_lookup)
+            .await
+    }
+
+    pub async fn prefetch_contract_names(&self, addresses: &[String]) -> Result<()> {
+        self.prefetch(addresses, contract_lookup, contract_batch_lookup)
+            .await
+    }
+
+    /// Returns signatures from the local resolvers only. Never blocks on the network.
+    pub fn local_function_signatures(&self, selector: &str) -> Vec<String> {
+        self.local_lookup(selector, function_lookup)
+    }
+
+    /// Returns custom errors from the local resolvers. No remote source knows about errors.
+    pub fn local_error_signatures(&self, selector: &str) -> Vec<String> {
+        self.local_lookup(selector, error_lookup)
+    }
+
+    /// Returns events from the local resolvers. No remote source knows about events.
+    pub fn local_event_signatures(&self, topic: &str) -> Vec<EventSignature> {
+        self.local_lookup(topic, event_lookup)
+    }
+
+    /// Returns signatures if they can be determined without a remote query.
+    pub fn cached_function_signatures(&self, selector: &str) -> Option<Vec<String>> {
+        self.cached_lookup(selector, function_lookup)
+    }
+
+    /// Returns the contract names if they can be determined without a remote query.
+    pub fn cached_contract_names(&self, address: &str) -> Option<Vec<AddressEntry>> {
+        self.cached_lookup(address, contract_lookup)
+    }
+
+    pub async fn function_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        self.lookup(selector, function_lookup).await
+    }
+
+    pub async fn contract_names(&self, address: &str) -> Result<Vec<AddressEntry>> {
+        self.lookup(address, contract_lookup).await
+    }
+
+    /// Marks the key as being looked up. Returns false if a lookup is already in progress.
+    pub fn start_lookup(&self, key: &str) -> bool {
+        self.in_flight.insert(key.to_lowercase(), ()).is_none()
+    }
+
+    pub fn finish_lookup(&self, key: &str) {
+        self.in_flight.remove(&key.to_lowercase());
+    }
+}
+
+impl Default for NameLookup {
+    fn default() -> NameLookup {
+        NameLookup::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Remote resolver that knows every selector and counts its queries.
+    #[derive(Debug, Default)]
+    struct CountingResolver {
+        queries: AtomicUsize,
+        batches: AtomicUsize,
+    }
+
+    #[tower_lsp::async_trait]
+    impl Resolver for CountingResolver {
+        fn is_remote(&self) -> bool {
+            true
+        }
+
+        async fn function_signatures(&self, selector: &str) -> Result<Vec<String>> {
+            self.queries.fetch_add(1, Ordering::SeqCst);
+            Ok(vec![format!("remote_{}()", selector)])
+        }
+
+        async fn function_signatures_batch(
+            &self,
+            selectors: &[String],
+        ) -> Result<HashMap<String, Vec<String>>> {
+            self.batches.fetch_add(1, Ordering::SeqCst);
+            Ok(selectors
+                .iter()
+                .map(|selector| (selector.clone(), vec![format!("remote_{}()", selector)]))
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_chain() {
+        let name_lookup = NameLookup::new();
+        name_lookup.set_resolvers(
+            &[ResolverKind::SignatureDatabase],
+            None,
+            DuneConfig::default(),
+        );
+        let remote = Arc::new(CountingResolver::default());
+        name_lookup.add_resolver(remote.clone());
+
+        // Known locally, so the remote resolver is not asked.
+        assert_eq!(
+            name_lookup.cached_function_signatures("0xa9059cbb"),
+            Some(vec!["transfer(address,uint256)".to_string()])
+        );
+        assert_eq!(name_lookup.cached_function_signatures("0x12345678"), None);
+        assert_eq!(
+            name_lookup.local_function_signatures("0x12345678"),
+            Vec::<String>::new()
+        );
+
+        // Queried once, then cached.
+        for _ in 0..2 {
+            assert_eq!(
+                name_lookup.function_signatures("0x12345678").await.unwrap(),
+                vec!["remote_0x12345678()"]
+            );
+        }
+        assert_eq!(remote.queries.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            name_lookup.cached_function_signatures("0x12345678"),
+            Some(vec!["remote_0x12345678()".to_string()])
+        );
+    }
+
+    #[test]
+    fn unconfigured_dune_is_skipped() {
+        let name_lookup = NameLookup::new();
+        name_lookup.set_resolvers(
+            &[ResolverKind::SignatureDatabase, ResolverKind::Dune],
+            None,
+            DuneConfig::default(),
+        );
+
+        assert!(!name_lookup.uses_remote_resolvers());
+        assert_eq!(
+            name_lookup.cached_function_signatures("0x12345678"),
+            Some(vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn prefetch() {
+        let name_lookup = NameLookup::new();
+        name_lookup.set_resolvers(
+            &[ResolverKind::SignatureDatabase],
+            None,
+            DuneConfig::default(),
+        );
+        let remote = Arc::new(CountingResolver::default());
+        name_lookup.add_resolver(remote.clone());
+
+        let selectors = ["0xa9059cbb", "0x12345678", "0x87654321", "0x12345678"].map(String::from);
+        name_lookup
+            .prefetch_function_signatures(&selectors)
+            .await
+            .unwrap();
+        // Known locally, so not part of the batch.
+        assert_eq!(
+            name_lookup.inspect_cache(Some("0xa9059cbb"))["entry"]["signatures"],
+            Value::Null
+        );
+        assert_eq!(
+            name_lookup.cached_function_signatures("0x87654321"),
+            Some(vec!["remote_0x87654321()".to_string()])
+        );
+
+        // Everything is cached now, so neither batches nor single queries are made.
+        name_lookup
+            .prefetch_function_signatures(&selectors)
+            .await
+            .unwrap();
+        name_lookup.function_signatures("0x12345678").await.unwrap();
+        assert_eq!(remote.batches.load(Ordering::SeqCst), 1);
+        assert_eq!(remote.queries.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,228 @@
+use crate::abi_artifacts::{ArtifactIndex, EventSignature};
+use crate::address_book::{AddressBook, AddressEntry};
+use crate::dune_apis::DuneClient;
+use crate::signature_db::SignatureDatabase;
+use eyre::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, RwLock};
+
+/// A source of names for the hex literals found in Yul code.
+///
+/// All lookups default to finding nothing, so that sources only implement the ones they know
+/// about. Keys are given in hex with `0x` prefix, in any case.
+#[tower_lsp::async_trait]
+pub trait Resolver: Debug + Send + Sync {
+    /// Tells whether lookups need network access. Lookups of other resolvers must complete
+    /// without waiting, which allows using them outside of async code.
+    fn is_remote(&self) -> bool {
+        false
+    }
+
+    /// Tells whether the resolver can answer lookups at all, e.g. whether credentials are
+    /// configured. Unavailable resolvers are skipped.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Returns the function signatures whose selector matches.
+    async fn function_signatures(&self, _selector: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Returns the custom error signatures whose selector matches.
+    async fn error_signatures(&self, _selector: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    /// Returns the events whose topic matches.
+    async fn event_signatures(&self, _topic: &str) -> Result<Vec<EventSignature>> {
+        Ok(vec![])
+    }
+
+    /// Returns the names of the contracts deployed at the address.
+    async fn contract_names(&self, _address: &str) -> Result<Vec<AddressEntry>> {
+        Ok(vec![])
+    }
+
+    /// Looks up several selectors at once. The result contains the lowercase selectors that
+    /// were looked up successfully, including ones without signatures.
+    async fn function_signatures_batch(
+        &self,
+        selectors: &[String],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        let mut results = HashMap::new();
+        for selector in selectors {
+            results.insert(
+                selector.to_lowercase(),
+                self.function_signatures(selector).await?,
+            );
+        }
+        Ok(results)
+    }
+
+    /// Looks up several addresses at once, like `function_signatures_batch`.
+    async fn contract_names_batch(
+        &self,
+        addresses: &[String],
+    ) -> Result<HashMap<String, Vec<AddressEntry>>> {
+        let mut results = HashMap::new();
+        for address in addresses {
+            results.insert(address.to_lowercase(), self.contract_names(address).await?);
+        }
+        Ok(results)
+    }
+}
+
+/// Resolvers that can be enabled in the settings, in the order in which they are consulted.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ResolverKind {
+    Artifacts,
+    AddressBook,
+    SignatureDatabase,
+    Dune,
+}
+
+impl ResolverKind {
+    /// Local sources come first, so that Dune is only queried for unknown values.
+    pub fn default_order() -> Vec<ResolverKind> {
+        vec![
+            ResolverKind::Artifacts,
+            ResolverKind::AddressBook,
+            ResolverKind::SignatureDatabase,
+            ResolverKind::Dune,
+        ]
+    }
+}
+
+#[derive(Debug)]
+pub struct SignatureDatabaseResolver {
+    pub database: Arc<RwLock<SignatureDatabase>>,
+}
+
+#[tower_lsp::async_trait]
+impl Resolver for SignatureDatabaseResolver {
+    async fn function_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        Ok(self.database.read().unwrap().lookup(selector))
+    }
+
+    // The database does not tell functions and errors apart.
+    async fn error_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        Ok(self.database.read().unwrap().lookup(selector))
+    }
+
+    async fn event_signatures(&self, topic: &str) -> Result<Vec<EventSignature>> {
+        Ok(self.database.read().unwrap().lookup_events(topic))
+    }
+}
+
+#[derive(Debug)]
+pub struct ArtifactResolver {
+    pub artifacts: Arc<RwLock<ArtifactIndex>>,
+}
+
+#[tower_lsp::async_trait]
+impl Resolver for ArtifactResolver {
+    async fn function_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        Ok(self.artifacts.read().unwrap().function_signatures(selector))
+    }
+
+    async fn error_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        Ok(self.artifacts.read().unwrap().error_signatures(selector))
+    }
+
+    async fn event_signatures(&self, topic: &str) -> Result<Vec<EventSignature>> {
+        Ok(self.artifacts.read().unwrap().event_signatures(topic))
+    }
+}
+
+#[derive(Debug)]
+pub struct AddressBookResolver {
+    pub address_book: Arc<RwLock<AddressBook>>,
+    pub chain_id: Option<u64>,
+}
+
+#[tower_lsp::async_trait]
+impl Resolver for AddressBookResolver {
+    async fn contract_names(&self, address: &str) -> Result<Vec<AddressEntry>> {
+        Ok(self
+            .address_book
+            .read()
+            .unwrap()
+            .lookup(address, self.chain_id))
+    }
+}
+
+/// Queries Dune. Finds nothing if no API key is available.
+#[derive(Debug)]
+pub struct DuneResolver {
+    pub dune_client: DuneClient,
+}
+
+#[tower_lsp::async_trait]
+impl Resolver for DuneResolver {
+    fn is_remote(&self) -> bool {
+        true
+    }
+
+    fn is_available(&self) -> bool {
+        self.dune_client.is_configured()
+    }
+
+    async fn function_signatures(&self, selector: &str) -> Result<Vec<String>> {
+        if !self.is_available() {
+            return Ok(vec![]);
+        }
+        Ok(self
+            .dune_client
+            .get_function_name(&selector.to_lowercase())
+            .await?)
+    }
+
+    async fn contract_names(&self, address: &str) -> Result<Vec<AddressEntry>> {
+        if !self.is_available() {
+            return Ok(vec![]);
+        }
+        let names = self
+            .dune_client
+            .get_contract_names(&address.to_lowercase())
+            .await?;
+        Ok(dune_entries(names))
+    }
+
+    async fn function_signatures_batch(
+        &self,
+        selectors: &[String],
+    ) -> Result<HashMap<String, Vec<String>>> {
+        if !self.is_available() {
+            return Ok(HashMap::new());
+        }
+        Ok(self.dune_client.get_function_names_batch(selectors).await?)
+    }
+
+    async fn contract_names_batch(
+        &self,
+        addresses: &[String],
+    ) -> Result<HashMap<String, Vec<AddressEntry>>> {
+        if !self.is_available() {
+            return Ok(HashMap::new());
+        }
+        let names = self.dune_client.get_contract_names_batch(addresses).await?;
+        Ok(names
+            .into_iter()
+            .map(|(address, names)| (address, dune_entries(names)))
+            .collect())
+    }
+}
+
+fn dune_entries(names: Vec<String>) -> Vec<AddressEntry> {
+    names
+        .into_iter()
+        .map(|name| AddressEntry {
+            name,
+            chain_id: None,
+        })
+        .collect()
+}
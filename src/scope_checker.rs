@@ -0,0 +1,233 @@
+use crate::diagnostics::Finding;
+use std::collections::HashMap;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{
+    Block, ForLoop, FunctionDefinition, Identifier, Statement, VariableDeclaration,
+};
+
+/// Reports declarations that shadow an identifier visible in an enclosing scope and functions
+/// defined twice in the same block.
+///
+/// Yul does not allow shadowing even across function boundaries, i.e. a function body cannot
+/// redeclare a variable from the outer block even though it cannot reference it.
+struct ScopeChecker {
+    // Each scope maps names to their declarations. Names are enough to find the previous
+    // declaration, since shadowing is forbidden and each name is declared once along the
+    // chain of enclosing scopes.
+    pub scopes: Vec<HashMap<String, Identifier>>,
+    pub findings: Vec<Finding>,
+}
+
+impl ScopeChecker {
+    pub fn new() -> ScopeChecker {
+        ScopeChecker {
+            scopes: vec![],
+            findings: vec![],
+        }
+    }
+
+    /// Returns the visible declaration of the name and whether it is in the innermost scope.
+    fn lookup(&self, name: &str) -> Option<(&Identifier, bool)> {
+        let innermost = self.scopes.len() - 1;
+        self.scopes
+            .iter()
+            .enumerate()
+            .rev()
+            .find_map(|(index, scope)| Some((scope.get(name)?, index == innermost)))
+    }
+
+    fn declare(&mut self, identifier: &Identifier) {
+        if let Some((previous, same_scope)) = self.lookup(&identifier.name) {
+            if let (Some(location), Some(previous_location)) =
+                (&identifier.location, &previous.location)
+            {
+                let message = if same_scope {
+                    format!("'{}' is already declared in this block.", identifier.name)
+                } else {
+                    format!(
+                        "Declaration of '{}' shadows an identifier visible in an enclosing scope.",
+                        identifier.name
+                    )
+                };
+                let finding = Finding::error(location.clone(), message).with_related(
+                    previous_location.clone(),
+                    format!("'{}' is declared here.", identifier.name),
+                );
+                self.findings.push(finding);
+            }
+        }
+
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(identifier.name.clone(), identifier.clone());
+    }
+
+    fn declare_function(&mut self, name: &Identifier) {
+        let duplicate = self.scopes.last().unwrap().get(&name.name).cloned();
+        match duplicate {
+            Some(previous) => {
+                if let (Some(location), Some(previous_location)) =
+                    (&name.location, &previous.location)
+                {
+                    let finding = Finding::error(
+                        location.clone(),
+                        format!("Function '{}' is already defined in this block.", name.name),
+                    )
+                    .with_related(
+                        previous_location.clone(),
+                        format!("Previous definition of '{}'.", name.name),
+                    );
+                    self.findings.push(finding);
+                }
+            }
+            None => self.declare(name),
+        }
+    }
+
+    /// Visits statements in the innermost scope without opening a new one.
+    fn visit_statements(&mut self, statements: &[Statement]) {
+        // Functions are visible in the whole block, including statements preceding them.
+        for statement in statements {
+            if let Statement::FunctionDefinition(function) = statement {
+                self.declare_function(&function.name);
+            }
+        }
+
+        for statement in statements {
+            self.visit_statement(statement);
+        }
+    }
+}
+
+impl ASTVisitor for ScopeChecker {
+    fn visit_block(&mut self, block: &Block) {
+        self.scopes.push(HashMap::new());
+        self.visit_statements(&block.statements);
+        self.scopes.pop();
+    }
+
+    fn visit_function_definition(&mut self, function: &FunctionDefinition) {
+        // The name has already been declared when entering the enclosing block.
+        self.scopes.push(HashMap::new());
+        for parameter in function.parameters.iter().chain(function.returns.iter()) {
+            self.declare(parameter);
+        }
+        self.visit_block(&function.body);
+        self.scopes.pop();
+    }
+
+    fn visit_variable_declaration(&mut self, declaration: &VariableDeclaration) {
+        // Variables are not visible in their own initializer.
+        if let Some(value) = &declaration.value {
+            self.visit_expression(value);
+        }
+        for variable in &declaration.variables {
+            self.declare(variable);
+        }
+    }
+
+    fn visit_for(&mut self, for_loop: &ForLoop) {
+        // Declarations from the init block remain visible in the rest of the loop.
+        self.scopes.push(HashMap::new());
+        self.visit_statements(&for_loop.pre.statements);
+        self.visit_expression(&for_loop.condition);
+        self.visit_block(&for_loop.post);
+        self.visit_block(&for_loop.body);
+        self.scopes.pop();
+    }
+}
+
+pub fn check_scopes(ast: &Block) -> Vec<Finding> {
+    let mut scope_checker = ScopeChecker::new();
+    scope_checker.visit_block(ast);
+    scope_checker.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+    use yultsur::yul::SourceLocation;
+    use yultsur::yul_parser::parse_block;
+
+    #[test]
+    fn erc20_no_findings() {
+        let source_code = read_to_string("examples/erc20.yul").unwrap();
+        let ast = parse_block(&source_code).unwrap();
+
+        assert_eq!(check_scopes(&ast), vec![]);
+    }
+
+    #[test]
+    fn variable_shadowed_in_function() {
+        let source_code = indoc! {"
+            {
+                let x := 1
+                function f() {
+                    let x := 2
+                }
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_scopes(&ast);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location, SourceLocation { start: 48, end: 49 });
+        assert_eq!(findings[0].related.len(), 1);
+        assert_eq!(
+            findings[0].related[0].location,
+            SourceLocation { start: 10, end: 11 }
+        );
+    }
+
+    #[test]
+    fn redeclaration_in_same_block() {
+        let source_code = indoc! {"
+            {
+                let x := 1
+                let x := 2
+                function f() {}
+                let f := 3
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_scopes(&ast);
+
+        let messages: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "'x' is already declared in this block.",
+                "'f' is already declared in this block.",
+            ]
+        );
+        assert_eq!(
+            findings[0].related[0].location,
+            SourceLocation { start: 10, end: 11 }
+        );
+    }
+
+    #[test]
+    fn duplicate_function() {
+        let source_code = indoc! {"
+            {
+                function f() {}
+                function f() {}
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_scopes(&ast);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location, SourceLocation { start: 35, end: 36 });
+        assert_eq!(
+            findings[0].related[0].location,
+            SourceLocation { start: 15, end: 16 }
+        );
+    }
+}
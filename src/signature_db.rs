@@ -0,0 +1,218 @@
+use crate::abi::{canonical_signature, format_bytes, keccak256, selector, split_parameters};
+use crate::abi_artifacts::EventSignature;
+use eyre::{eyre, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Signatures shipped with the server, so that common selectors resolve without any setup.
+const BUNDLED_SIGNATURES: &str = include_str!("../data/signatures.txt");
+
+/// Local database of function and error signatures indexed by selector, and of events
+/// indexed by topic.
+///
+/// Selectors and topics are computed locally, so lookups need no network access.
+#[derive(Clone, Debug, Default)]
+pub struct SignatureDatabase {
+    // Keys are selectors and topics in lowercase hex with `0x` prefix.
+    signatures: HashMap<String, Vec<String>>,
+    events: HashMap<String, Vec<EventSignature>>,
+}
+
+impl SignatureDatabase {
+    pub fn new() -> SignatureDatabase {
+        SignatureDatabase::default()
+    }
+
+    /// Creates a database containing the signatures bundled with the server.
+    pub fn bundled() -> SignatureDatabase {
+        let mut database = SignatureDatabase::new();
+        database.add_text(BUNDLED_SIGNATURES);
+        database
+    }
+
+    /// Adds a signature unless it is already present. Returns whether it is a valid signature,
+    /// i.e. true for signatures that were present before.
+    pub fn add(&mut self, signature: &str) -> bool {
+        let signature = match canonical_signature(signature) {
+            Some(signature) => signature,
+            None => return false,
+        };
+
+        let candidates = self
+            .signatures
+            .entry(format_bytes(&selector(&signature)))
+            .or_default();
+        if !candidates.contains(&signature) {
+            candidates.push(signature);
+        }
+        true
+    }
+
+    /// Adds an event declaration like `Transfer(address indexed from, address indexed to,
+    /// uint256 value)`. The `indexed` keywords determine the number of topics. Returns whether
+    /// it is a valid declaration, like `add`.
+    pub fn add_event(&mut self, declaration: &str) -> bool {
+        let declaration = declaration.trim();
+        let signature = match canonical_signature(declaration) {
+            Some(signature) => signature,
+            None => return false,
+        };
+        let parameters = &declaration[declaration.find('(').unwrap() + 1..declaration.len() - 1];
+        let indexed_count = split_parameters(parameters)
+            .into_iter()
+            .filter(|parameter| parameter.split_whitespace().any(|word| word == "indexed"))
+            .count();
+
+        let event = EventSignature {
+            signature,
+            indexed_count,
+            anonymous: false,
+        };
+        let candidates = self
+            .events
+            .entry(format_bytes(&keccak256(event.signature.as_bytes())))
+            .or_default();
+        if !candidates.contains(&event) {
+            candidates.push(event);
+        }
+        true
+    }
+
+    /// Adds signatures from text with one signature per line. Lines starting with `event`
+    /// declare events. Empty lines and lines starting with `#` are ignored. Returns the number
+    /// of valid signatures, including ones that were present before.
+    pub fn add_text(&mut self, text: &str) -> usize {
+        text.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter(|line| match line.strip_prefix("event ") {
+                Some(declaration) => self.add_event(declaration),
+                None => self.add(line),
+            })
+            .count()
+    }
+
+    /// Adds signatures from JSON. Accepts an array of signatures or an object whose values
+    /// are signatures or arrays of signatures, e.g. a map from selectors to signatures.
+    /// Selectors in the file are ignored and recomputed. Returns the number of valid
+    /// signatures, including ones that were present before.
+    pub fn add_json(&mut self, json: &Value) -> usize {
+        match json {
+            Value::String(signature) => self.add(signature) as usize,
+            Value::Array(values) => values.iter().map(|value| self.add_json(value)).sum(),
+            Value::Object(entries) => entries.values().map(|value| self.add_json(value)).sum(),
+            _ => 0,
+        }
+    }
+
+    /// Loads a user-supplied file. Files with the `.json` extension are parsed as JSON, all
+    /// others as text. Returns the number of valid signatures in the file, which may include
+    /// signatures that were present before.
+    pub fn load_file(&mut self, path: &Path) -> Result<usize> {
+        let content = read_to_string(path)?;
+        let valid = if path
+            .extension()
+            .map_or(false, |extension| extension == "json")
+        {
+            self.add_json(&serde_json::from_str(&content)?)
+        } else {
+            self.add_text(&content)
+        };
+
+        if valid == 0 {
+            return Err(eyre!("No valid signatures found in {}", path.display()));
+        }
+        Ok(valid)
+    }
+
+    /// Returns signatures matching a selector given in hex with `0x` prefix.
+    pub fn lookup(&self, selector: &str) -> Vec<String> {
+        self.signatures
+            .get(&selector.to_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns events matching a topic given in hex with `0x` prefix.
+    pub fn lookup_events(&self, topic: &str) -> Vec<EventSignature> {
+        self.events
+            .get(&topic.to_lowercase())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of function and error signatures.
+    pub fn len(&self) -> usize {
+        self.signatures.values().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn bundled_signatures() {
+        let database = SignatureDatabase::bundled();
+
+        assert_eq!(database.len(), 100);
+        assert_eq!(
+            database.lookup("0xa9059cbb"),
+            vec!["transfer(address,uint256)"]
+        );
+        assert_eq!(database.lookup("0x70A08231"), vec!["balanceOf(address)"]);
+        assert_eq!(database.lookup("0x08c379a0"), vec!["Error(string)"]);
+        assert_eq!(database.lookup("0x00000000"), Vec::<String>::new());
+
+        // ERC-20 and ERC-721 share the topic, but not the number of indexed parameters.
+        let transfers = database
+            .lookup_events("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+        assert_eq!(
+            transfers
+                .iter()
+                .map(|event| (event.signature.as_str(), event.indexed_count))
+                .collect::<Vec<_>>(),
+            vec![
+                ("Transfer(address,address,uint256)", 2),
+                ("Transfer(address,address,uint256)", 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn user_signatures() {
+        let mut database = SignatureDatabase::new();
+
+        assert_eq!(
+            database.add_text("# comment\n\nfoo(uint)\nnot a signature\n"),
+            1
+        );
+        assert_eq!(
+            database.add_json(&json!({"0x12345678": ["bar(address)", "foo(uint256)"]})),
+            2
+        );
+        assert_eq!(database.len(), 2);
+        assert_eq!(
+            database.lookup(&format_bytes(&selector("foo(uint256)"))),
+            vec!["foo(uint256)"]
+        );
+
+        // Signatures that are already present are valid, but not added again.
+        assert_eq!(
+            database.add_text(
+                "foo(uint256)
+bar(address)
+"
+            ),
+            2
+        );
+        assert_eq!(database.len(), 2);
+    }
+}
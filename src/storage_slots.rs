@@ -0,0 +1,245 @@
+use crate::abi::{format_bytes, keccak256};
+use crate::diagnostics::{Edit, Finding, Fix};
+use crate::literal_value::evaluate_literal;
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{Block, Literal, SourceLocation};
+
+/// Prefix of namespace ids in comments, as in Solidity's
+/// `@custom:storage-location erc7201:<id>` annotation.
+const ERC7201_PREFIX: &str = "erc7201:";
+
+/// EIP-1967 slots with the labels they are derived from.
+const EIP1967_SLOTS: &[(&str, &str)] = &[
+    ("implementation", "eip1967.proxy.implementation"),
+    ("admin", "eip1967.proxy.admin"),
+    ("beacon", "eip1967.proxy.beacon"),
+];
+
+/// Namespaces of the OpenZeppelin upgradeable contracts.
+const BUNDLED_NAMESPACES: &[&str] = &[
+    "openzeppelin.storage.AccessControl",
+    "openzeppelin.storage.EIP712",
+    "openzeppelin.storage.ERC1155",
+    "openzeppelin.storage.ERC20",
+    "openzeppelin.storage.ERC721",
+    "openzeppelin.storage.Initializable",
+    "openzeppelin.storage.Nonces",
+    "openzeppelin.storage.Ownable",
+    "openzeppelin.storage.Ownable2Step",
+    "openzeppelin.storage.Pausable",
+    "openzeppelin.storage.ReentrancyGuard",
+];
+
+fn minus_one(mut bytes: [u8; 32]) -> [u8; 32] {
+    for byte in bytes.iter_mut().rev() {
+        let (difference, borrow) = byte.overflowing_sub(1);
+        *byte = difference;
+        if !borrow {
+            break;
+        }
+    }
+    bytes
+}
+
+/// `bytes32(uint256(keccak256(label)) - 1)`
+fn eip1967_slot(label: &str) -> String {
+    format_bytes(&minus_one(keccak256(label.as_bytes())))
+}
+
+/// `keccak256(abi.encode(uint256(keccak256(id)) - 1)) & ~bytes32(uint256(0xff))`
+pub fn erc7201_slot(namespace_id: &str) -> String {
+    let mut slot = keccak256(&minus_one(keccak256(namespace_id.as_bytes())));
+    slot[31] = 0;
+    format_bytes(&slot)
+}
+
+/// Identifies a well-known slot or the root of one of the namespaces, given as 32 bytes in
+/// lowercase. Returns a description in Markdown.
+pub fn describe_storage_slot(slot: &str, namespaces: &[String]) -> Option<String> {
+    if let Some((name, label)) = EIP1967_SLOTS
+        .iter()
+        .find(|(_, label)| eip1967_slot(label) == slot)
+    {
+        return Some(format!(
+            "EIP-1967 {} slot\n\n`bytes32(uint256(keccak256(\"{}\")) - 1)`",
+            name, label
+        ));
+    }
+    let namespace_id = BUNDLED_NAMESPACES
+        .iter()
+        .copied()
+        .chain(namespaces.iter().map(String::as_str))
+        .find(|namespace_id| erc7201_slot(namespace_id) == slot)?;
+    Some(format!(
+        "ERC-7201 namespace `{}`\n\n`keccak256(abi.encode(uint256(keccak256(\"{}\")) - 1)) & ~bytes32(uint256(0xff))`",
+        namespace_id, namespace_id
+    ))
+}
+
+/// Returns the namespace id given by an `erc7201:<id>` comment on the line of the literal,
+/// or on the line before if that line holds only a comment.
+fn annotated_namespace(source: &str, location: &SourceLocation) -> Option<String> {
+    let line_start = source[..location.start]
+        .rfind('\n')
+        .map_or(0, |index| index + 1);
+    let line_end = source[location.end..]
+        .find('\n')
+        .map_or(source.len(), |index| location.end + index);
+    let previous_line = match line_start {
+        0 => "",
+        _ => {
+            let previous_start = source[..line_start - 1]
+                .rfind('\n')
+                .map_or(0, |index| index + 1);
+            source[previous_start..line_start - 1].trim_start()
+        }
+    };
+
+    let comment = match source[location.end..line_end].find(ERC7201_PREFIX) {
+        Some(index) => &source[location.end + index..line_end],
+        None if previous_line.starts_with("//") || previous_line.starts_with("/*") => {
+            &previous_line[previous_line.find(ERC7201_PREFIX)?..]
+        }
+        None => return None,
+    };
+    let namespace_id: String = comment[ERC7201_PREFIX.len()..]
+        .split_whitespace()
+        .next()?
+        .trim_end_matches("*/")
+        .to_string();
+    if namespace_id.is_empty() {
+        None
+    } else {
+        Some(namespace_id)
+    }
+}
+
+struct StorageSlotChecker<'a> {
+    pub source: &'a str,
+    pub findings: Vec<Finding>,
+}
+
+impl<'a> ASTVisitor for StorageSlotChecker<'a> {
+    fn visit_literal(&mut self, literal: &Literal) {
+        // Only full 32-byte words are slots, so other literals on the line are left alone.
+        if !literal.literal.starts_with("0x") || literal.literal.len() != 66 {
+            return;
+        }
+        let location = match &literal.location {
+            Some(location) => location,
+            None => return,
+        };
+        let namespace_id = match annotated_namespace(self.source, location) {
+            Some(namespace_id) => namespace_id,
+            None => return,
+        };
+        let value = match evaluate_literal(&literal.literal) {
+            Ok(value) => format_bytes(&value.0),
+            Err(_) => return,
+        };
+
+        let expected = erc7201_slot(&namespace_id);
+        if value != expected {
+            self.findings.push(
+                Finding::warning(
+                    location.clone(),
+                    format!(
+                        "Storage slot does not match ERC-7201 namespace '{}'. Expected {}.",
+                        namespace_id, expected
+                    ),
+                )
+                .with_fix(Fix {
+                    title: format!("Change to the root of '{}'", namespace_id),
+                    edits: vec![Edit {
+                        location: location.clone(),
+                        replacement: expected,
+                    }],
+                }),
+            );
+        }
+    }
+}
+
+/// Checks 32-byte literals annotated with an `erc7201:<id>` comment against the root of
+/// that namespace.
+pub fn check_storage_slots(source: &str, ast: &Block) -> Vec<Finding> {
+    let mut storage_slot_checker = StorageSlotChecker {
+        source,
+        findings: vec![],
+    };
+    storage_slot_checker.visit_block(ast);
+    storage_slot_checker.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use yultsur::yul_parser::parse_block;
+
+    #[test]
+    fn derivations() {
+        assert_eq!(
+            eip1967_slot("eip1967.proxy.implementation"),
+            "0x360894a13ba1a3210667c828492db98dca3e2076cc3735a920a3ca505d382bbc"
+        );
+        assert_eq!(
+            eip1967_slot("eip1967.proxy.admin"),
+            "0xb53127684a568b3173ae13b9f8a6016e243e63b6e8ee1178d6a717850b5d6103"
+        );
+        // From the example in ERC-7201.
+        assert_eq!(
+            erc7201_slot("example.main"),
+            "0x183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500"
+        );
+    }
+
+    #[test]
+    fn slot_descriptions() {
+        assert_eq!(
+            describe_storage_slot(
+                "0xa3f0ad74e5423aebfd80d3ef4346578335a9a72aeaee59ff6cb3582b35133d50",
+                &[]
+            )
+            .unwrap(),
+            "EIP-1967 beacon slot\n\n`bytes32(uint256(keccak256(\"eip1967.proxy.beacon\")) - 1)`"
+        );
+        let slot = erc7201_slot("example.main");
+        assert!(describe_storage_slot(&slot, &[]).is_none());
+        assert!(describe_storage_slot(&slot, &["example.main".to_string()])
+            .unwrap()
+            .starts_with("ERC-7201 namespace `example.main`"));
+        assert!(
+            describe_storage_slot(&erc7201_slot("openzeppelin.storage.Ownable"), &[]).is_some()
+        );
+    }
+
+    #[test]
+    fn annotated_slots() {
+        let source_code = indoc! {"
+            {
+                let main := 0x183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500 // erc7201:example.main
+                // erc7201:example.other
+                let other := 0x183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500
+                sstore(0x183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab500, 1) /* erc7201:example.main */
+                let unannotated := 0x183a6125c38840424c4a85fa12bab2ab606c4b6d0e7cc73c0c06ba5300eab501
+            }
+        "};
+        let ast = parse_block(source_code).unwrap();
+        let findings = check_storage_slots(source_code, &ast);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].message,
+            format!(
+                "Storage slot does not match ERC-7201 namespace 'example.other'. Expected {}.",
+                erc7201_slot("example.other")
+            )
+        );
+        assert_eq!(source_code[..findings[0].location.start].lines().count(), 4);
+        assert_eq!(
+            findings[0].fix.as_ref().unwrap().edits[0].replacement,
+            erc7201_slot("example.other")
+        );
+    }
+}
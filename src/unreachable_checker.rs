@@ -0,0 +1,172 @@
+use crate::control_flow::{collect_functions, find_terminating_functions, ControlFlowGraph};
+use crate::diagnostics::Finding;
+use std::collections::HashSet;
+use yultsur::yul::{Block, Expression, SourceLocation, Statement};
+
+fn expression_location(expression: &Expression) -> Option<SourceLocation> {
+    match expression {
+        Expression::Literal(literal) => literal.location.clone(),
+        Expression::Identifier(identifier) => identifier.location.clone(),
+        Expression::FunctionCall(function_call) => function_call.function.location.clone(),
+    }
+}
+
+/// Returns the location of the first token of the statement that has one.
+fn statement_location(statement: &Statement) -> Option<SourceLocation> {
+    match statement {
+        Statement::Expression(expression) => expression_location(expression),
+        Statement::VariableDeclaration(declaration) => {
+            declaration.variables.first()?.location.clone()
+        }
+        Statement::Assignment(assignment) => assignment.variables.first()?.location.clone(),
+        Statement::If(if_statement) => expression_location(&if_statement.condition),
+        Statement::Switch(switch) => expression_location(&switch.expression),
+        Statement::ForLoop(for_loop) => expression_location(&for_loop.condition),
+        Statement::Block(block) => block.statements.iter().find_map(statement_location),
+        Statement::FunctionDefinition(function) => function.name.location.clone(),
+        _ => None,
+    }
+}
+
+struct UnreachableReporter {
+    // Start offsets of statements placed in unreachable blocks of any control-flow graph.
+    pub unreachable: HashSet<usize>,
+    pub findings: Vec<Finding>,
+}
+
+impl UnreachableReporter {
+    /// Reports the first statement of every sequence of unreachable statements, so that a
+    /// single dead region produces a single warning.
+    fn report_statements(&mut self, statements: &[Statement], parent_reachable: bool) {
+        let mut previous_reachable = parent_reachable;
+        // Set when a dead region starts and cleared once it has been reported. Some
+        // statements, e.g. `break`, have no location and cannot be reported themselves.
+        let mut pending_report = false;
+
+        for statement in statements {
+            if let Statement::FunctionDefinition(function) = statement {
+                // The body is analyzed separately. Its entry is always considered reachable.
+                self.report_statements(&function.body.statements, true);
+                continue;
+            }
+
+            // Statements without a location, e.g. `break`, cannot start a reported region, so
+            // they keep the reachability of the statement before them.
+            let reachable = match statement_location(statement) {
+                Some(location) => !self.unreachable.contains(&location.start),
+                None => previous_reachable,
+            };
+            if reachable {
+                pending_report = false;
+            } else if previous_reachable {
+                pending_report = true;
+            }
+            if pending_report {
+                if let Some(location) = statement_location(statement) {
+                    self.findings.push(
+                        Finding::warning(location, "Unreachable code.".to_string()).unnecessary(),
+                    );
+                    pending_report = false;
+                }
+            }
+            previous_reachable = reachable;
+
+            for block in child_blocks(statement) {
+                self.report_statements(&block.statements, reachable);
+            }
+        }
+    }
+}
+
+fn child_blocks(statement: &Statement) -> Vec<&Block> {
+    match statement {
+        Statement::Block(block) => vec![block],
+        Statement::If(if_statement) => vec![&if_statement.body],
+        Statement::Switch(switch) => switch.cases.iter().map(|case| &case.body).collect(),
+        Statement::ForLoop(for_loop) => vec![&for_loop.pre, &for_loop.body, &for_loop.post],
+        _ => vec![],
+    }
+}
+
+/// Reports statements that can never be executed because they follow a statement that
+/// always terminates, leaves the function or jumps out of a loop.
+pub fn check_unreachable(ast: &Block) -> Vec<Finding> {
+    let terminating_functions = find_terminating_functions(ast);
+
+    let mut bodies = vec![ast];
+    bodies.extend(
+        collect_functions(ast)
+            .into_iter()
+            .map(|function| &function.body),
+    );
+
+    let mut unreachable = HashSet::new();
+    for body in bodies {
+        let graph = ControlFlowGraph::build(body, &terminating_functions);
+        for (block, reachable) in graph.blocks.iter().zip(graph.reachable()) {
+            if !reachable {
+                unreachable.extend(
+                    block
+                        .statements
+                        .iter()
+                        .filter_map(|statement| statement_location(statement))
+                        .map(|location| location.start),
+                );
+            }
+        }
+    }
+
+    let mut reporter = UnreachableReporter {
+        unreachable,
+        findings: vec![],
+    };
+    reporter.report_statements(&ast.statements, true);
+    reporter.findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+    use yultsur::dialect::EVMDialect;
+    use yultsur::resolver::resolve;
+    use yultsur::yul_parser::parse_block;
+
+    fn findings_for(source_code: &str) -> Vec<Finding> {
+        let mut ast = parse_block(source_code).unwrap();
+        resolve::<EVMDialect>(&mut ast);
+        check_unreachable(&ast)
+    }
+
+    #[test]
+    fn erc20_no_unreachable_code() {
+        let source_code = read_to_string("examples/erc20.yul").unwrap();
+
+        assert_eq!(findings_for(&source_code), vec![]);
+    }
+
+    #[test]
+    fn code_after_terminating_statements() {
+        let source_code = indoc! {"
+            {
+                function fail() { revert(0, 0) }
+                for {} 1 {} {
+                    break
+                    mstore(0, 1)
+                }
+                fail()
+                let x := 1
+                sstore(0, x)
+            }
+        "};
+        let findings = findings_for(source_code);
+        let locations: Vec<&str> = findings
+            .iter()
+            .map(|finding| &source_code[finding.location.start..finding.location.end])
+            .collect();
+
+        assert_eq!(locations, vec!["mstore", "x"]);
+        assert!(findings.iter().all(|finding| finding.unnecessary));
+    }
+}
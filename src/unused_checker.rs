@@ -0,0 +1,411 @@
+use crate::diagnostics::{Edit, Finding, Fix};
+use std::collections::{HashMap, HashSet};
+use yultsur::visitor::ASTVisitor;
+use yultsur::yul::{
+    Assignment, Block, Expression, FunctionCall, FunctionDefinition, Identifier, IdentifierID,
+    SourceLocation, VariableDeclaration,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum DeclarationKind {
+    Function,
+    Parameter,
+    ReturnVariable,
+    Variable,
+}
+
+struct Declaration {
+    pub identifier: Identifier,
+    pub kind: DeclarationKind,
+    // End of the `let` statement if it can be removed without changing behavior, i.e. it
+    // declares a single variable and its initializer has no side effects.
+    pub removable_end: Option<usize>,
+}
+
+struct UsageCollector {
+    pub declarations: Vec<Declaration>,
+    pub reads: HashSet<u64>,
+    pub assignments: HashSet<u64>,
+    // Functions called from each function, keyed by the id of the caller.
+    // Calls made from the top-level code, which is the entry point, are stored under None.
+    pub calls: HashMap<Option<u64>, HashSet<u64>>,
+    pub current_function: Option<u64>,
+}
+
+impl UsageCollector {
+    pub fn new() -> UsageCollector {
+        UsageCollector {
+            declarations: vec![],
+            reads: HashSet::new(),
+            assignments: HashSet::new(),
+            calls: HashMap::new(),
+            current_function: None,
+        }
+    }
+
+    fn declare(&mut self, identifier: &Identifier, kind: DeclarationKind) {
+        self.declare_removable(identifier, kind, None);
+    }
+
+    fn declare_removable(
+        &mut self,
+        identifier: &Identifier,
+        kind: DeclarationKind,
+        removable_end: Option<usize>,
+    ) {
+        self.declarations.push(Declaration {
+            identifier: identifier.clone(),
+            kind,
+            removable_end,
+        });
+    }
+
+    fn reachable_functions(&self) -> HashSet<u64> {
+        let mut reachable = HashSet::new();
+        let mut pending: Vec<u64> = self
+            .calls
+            .get(&None)
+            .map(|callees| callees.iter().copied().collect())
+            .unwrap_or_default();
+
+        while let Some(function) = pending.pop() {
+            if reachable.insert(function) {
+                if let Some(callees) = self.calls.get(&Some(function)) {
+                    pending.extend(callees.iter().copied());
+                }
+            }
+        }
+        reachable
+    }
+
+    fn has_callers(&self, function: u64) -> bool {
+        self.calls
+            .iter()
+            .any(|(caller, callees)| *caller != Some(function) && callees.contains(&function))
+    }
+}
+
+impl ASTVisitor for UsageCollector {
+    fn visit_function_definition(&mut self, function: &FunctionDefinition) {
+        self.declare(&function.name, DeclarationKind::Function);
+        for parameter in &function.parameters {
+            self.declare(parameter, DeclarationKind::Parameter);
+        }
+        for return_variable in &function.returns {
+            self.declare(return_variable, DeclarationKind::ReturnVariable);
+        }
+
+        let outer_function = self.current_function;
+        if let IdentifierID::Declaration(id) = function.name.id {
+            self.current_function = Some(id);
+        }
+        self.visit_block(&function.body);
+        self.current_function = outer_function;
+    }
+
+    fn visit_variable_declaration(&mut self, declaration: &VariableDeclaration) {
+        let removable_end = match (declaration.variables.as_slice(), &declaration.value) {
+            ([variable], None) => variable.location.as_ref().map(|location| location.end),
+            ([_], Some(Expression::Literal(literal))) => {
+                literal.location.as_ref().map(|location| location.end)
+            }
+            ([_], Some(Expression::Identifier(identifier))) => {
+                identifier.location.as_ref().map(|location| location.end)
+            }
+            _ => None,
+        };
+
+        for variable in &declaration.variables {
+            self.declare_removable(variable, DeclarationKind::Variable, removable_end);
+        }
+        if let Some(value) = &declaration.value {
+            self.visit_expression(value);
+        }
+    }
+
+    fn visit_assignment(&mut self, assignment: &Assignment) {
+        for variable in &assignment.variables {
+            if let IdentifierID::Reference(id) = variable.id {
+                self.assignments.insert(id);
+            }
+        }
+        self.visit_expression(&assignment.value);
+    }
+
+    fn visit_function_call(&mut self, function_call: &FunctionCall) {
+        if let IdentifierID::Reference(id) = function_call.function.id {
+            self.calls
+                .entry(self.current_function)
+                .or_default()
+                .insert(id);
+        }
+        for argument in &function_call.arguments {
+            self.visit_expression(argument);
+        }
+    }
+
+    fn visit_identifier(&mut self, identifier: &Identifier) {
+        if let IdentifierID::Reference(id) = identifier.id {
+            self.reads.insert(id);
+        }
+    }
+}
+
+/// Finds the end of the block starting at or after `start`, skipping over comments and
+/// string literals. Returns the offset just past the closing brace.
+fn find_block_end(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0;
+    let mut position = start;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(position + 1);
+                }
+            }
+            b'"' => {
+                position += 1;
+                while position < bytes.len() && bytes[position] != b'"' {
+                    if bytes[position] == b'\\' {
+                        position += 1;
+                    }
+                    position += 1;
+                }
+            }
+            b'/' if bytes.get(position + 1) == Some(&b'/') => {
+                while position < bytes.len() && bytes[position] != b'\n' {
+                    position += 1;
+                }
+            }
+            b'/' if bytes.get(position + 1) == Some(&b'*') => {
+                position = source[position + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |end| position + 2 + end + 1);
+            }
+            _ => {}
+        }
+        position += 1;
+    }
+    None
+}
+
+/// Widens the span to whole lines if nothing but whitespace surrounds it, so that removing
+/// it does not leave an empty line behind.
+fn widen_to_lines(source: &str, start: usize, end: usize) -> SourceLocation {
+    let line_start = source[..start].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = source[end..]
+        .find('\n')
+        .map_or(source.len(), |newline| end + newline + 1);
+
+    if source[line_start..start].trim().is_empty() && source[end..line_end].trim().is_empty() {
+        SourceLocation {
+            start: line_start,
+            end: line_end,
+        }
+    } else {
+        SourceLocation { start, end }
+    }
+}
+
+fn removal_fix(title: String, location: SourceLocation) -> Fix {
+    Fix {
+        title,
+        edits: vec![Edit {
+            location,
+            replacement: "".to_string(),
+        }],
+    }
+}
+
+fn function_removal(source: &str, name: &SourceLocation) -> Option<SourceLocation> {
+    let start = source[..name.start].rfind("function")?;
+    let end = find_block_end(source, name.end)?;
+    Some(widen_to_lines(source, start, end))
+}
+
+fn variable_removal(source: &str, variable: &SourceLocation, end: usize) -> Option<SourceLocation> {
+    let start = source[..variable.start].rfind("let")?;
+    Some(widen_to_lines(source, start, end))
+}
+
+/// Reports variables that are never read, parameters that are never used, return variables
+/// that are never assigned and functions that cannot be reached from the top-level code.
+pub fn check_unused(source: &str, ast: &Block) -> Vec<Finding> {
+    let mut collector = UsageCollector::new();
+    collector.visit_block(ast);
+    let reachable = collector.reachable_functions();
+
+    let mut findings = vec![];
+    for declaration in &collector.declarations {
+        let identifier = &declaration.identifier;
+        let (id, location) = match (&identifier.id, &identifier.location) {
+            (IdentifierID::Declaration(id), Some(location)) => (*id, location),
+            _ => continue,
+        };
+        let name = &identifier.name;
+
+        let finding = match declaration.kind {
+            DeclarationKind::Function if !reachable.contains(&id) => {
+                let message = if collector.has_callers(id) {
+                    format!("Function '{}' is only called from unused functions.", name)
+                } else {
+                    format!("Function '{}' is never called.", name)
+                };
+                let finding = Finding::warning(location.clone(), message).unnecessary();
+                match function_removal(source, location) {
+                    Some(removal) => finding.with_fix(removal_fix(
+                        format!("Remove unused function '{}'", name),
+                        removal,
+                    )),
+                    None => finding,
+                }
+            }
+            DeclarationKind::Parameter
+                if !collector.reads.contains(&id) && !collector.assignments.contains(&id) =>
+            {
+                Finding::warning(
+                    location.clone(),
+                    format!("Parameter '{}' is never used.", name),
+                )
+            }
+            DeclarationKind::ReturnVariable if !collector.assignments.contains(&id) => {
+                Finding::warning(
+                    location.clone(),
+                    format!("Return variable '{}' is never assigned.", name),
+                )
+            }
+            DeclarationKind::Variable if !collector.reads.contains(&id) => {
+                let finding = Finding::warning(
+                    location.clone(),
+                    format!("Variable '{}' is never read.", name),
+                )
+                .unnecessary();
+                // Removing a variable that is assigned later leaves the assignment undeclared.
+                if collector.assignments.contains(&id) {
+                    findings.push(finding);
+                    continue;
+                }
+                match declaration
+                    .removable_end
+                    .and_then(|end| variable_removal(source, location, end))
+                {
+                    Some(removal) => finding.with_fix(removal_fix(
+                        format!("Remove unused variable '{}'", name),
+                        removal,
+                    )),
+                    None => finding,
+                }
+            }
+            _ => continue,
+        };
+        // Parameters and return variables are part of the signature and cannot simply be
+        // removed, so only functions and variables are marked as unnecessary.
+        findings.push(finding);
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use std::fs::read_to_string;
+    use yultsur::dialect::EVMDialect;
+    use yultsur::resolver::resolve;
+    use yultsur::yul_parser::parse_block;
+
+    fn findings_for(source_code: &str) -> Vec<Finding> {
+        let mut ast = parse_block(source_code).unwrap();
+        resolve::<EVMDialect>(&mut ast);
+        check_unused(source_code, &ast)
+    }
+
+    #[test]
+    fn erc20_unused() {
+        let source_code = read_to_string("examples/erc20.yul").unwrap();
+        let messages: Vec<String> = findings_for(&source_code)
+            .into_iter()
+            .map(|finding| finding.message)
+            .collect();
+
+        assert_eq!(messages, vec!["Function 'gte' is never called.",]);
+    }
+
+    #[test]
+    fn unused_declarations() {
+        let source_code = indoc! {"
+            {
+                let a := 1
+                f(a)
+                function f(x, y) -> r {
+                    let z := x
+                }
+            }
+        "};
+        let findings = findings_for(source_code);
+        let messages: Vec<&str> = findings
+            .iter()
+            .map(|finding| finding.message.as_str())
+            .collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "Parameter 'y' is never used.",
+                "Return variable 'r' is never assigned.",
+                "Variable 'z' is never read.",
+            ]
+        );
+        assert!(!findings[0].unnecessary);
+        assert!(!findings[1].unnecessary);
+        assert!(findings[2].unnecessary);
+
+        let fix = findings[2].fix.as_ref().unwrap();
+        assert_eq!(fix.edits.len(), 1);
+        assert_eq!(
+            &source_code[fix.edits[0].location.start..fix.edits[0].location.end],
+            "        let z := x\n"
+        );
+    }
+
+    #[test]
+    fn assigned_variable_is_not_removed() {
+        let source_code = indoc! {"
+            {
+                let x := 0
+                x := 5
+            }
+        "};
+        let findings = findings_for(source_code);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].message, "Variable 'x' is never read.");
+        assert!(findings[0].unnecessary);
+        assert!(findings[0].fix.is_none());
+    }
+
+    #[test]
+    fn functions_unreachable_from_entry_point() {
+        let source_code = indoc! {"
+            {
+                function f() { g() }
+                function g() { f() }
+            }
+        "};
+        let findings = findings_for(source_code);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0].message,
+            "Function 'f' is only called from unused functions."
+        );
+        let fix = findings[0].fix.as_ref().unwrap();
+        assert_eq!(
+            &source_code[fix.edits[0].location.start..fix.edits[0].location.end],
+            "    function f() { g() }\n"
+        );
+    }
+}